@@ -0,0 +1,800 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use chrono::prelude::*;
+use libflate::gzip::Decoder as GzipReader;
+
+use warc::timestamp;
+use warc::validate::Validator;
+use warc::{
+    BufferedBody, Error, MappedWarc, MediaType, Record, RecordType, WarcHeader, WarcReader,
+    WarcSlice, WarcWriter, WarcWriterOptions,
+};
+
+const MB: usize = 1_048_576;
+
+const USAGE: &str = "\
+usage: warc <command> [options] <file>...
+
+commands:
+    ls <file>
+        list records with their offset, length, type, date and target URI
+    cat <file> (--index N | --id ID | --offset N)
+        print a single record
+    extract <file> [--dir DIR]
+        write the payloads of response and resource records to files
+    filter <file> <output> [--type TYPE]... [--uri-prefix PREFIX] [--from TIMESTAMP] [--to TIMESTAMP]
+        copy matching records to a new file, compressed if its name ends in .gz
+    index <file>...
+        print a sorted CDX index of the captures in the files
+    validate <file>...
+        check that every record conforms to the WARC specification, and verify its digests
+
+Files may be uncompressed, or compressed with GZIP. Offsets and lengths in compressed files are
+those of the GZIP member holding the record.";
+
+macro_rules! usage_err {
+    ($str:expr) => {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, $str.to_string())
+    };
+}
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let command = args.next().unwrap_or_default();
+    let args = Args::parse(args);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let result = run(&command, &args, &mut out);
+    let _ = out.flush();
+
+    match result {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            if err.downcast_ref::<io::Error>().map(io::Error::kind)
+                == Some(io::ErrorKind::InvalidInput)
+            {
+                eprintln!("\n{}", USAGE);
+            }
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Run a command, writing its output to `out`. Returns `false` if the input was found invalid.
+fn run<W: Write>(command: &str, args: &Args, out: &mut W) -> Result<bool> {
+    match command {
+        "ls" => ls(open(args.file(0)?, 0)?, out)?,
+        "cat" => cat(args, out)?,
+        "extract" => extract(args, out)?,
+        "filter" => filter(args)?,
+        "index" => index(args, out)?,
+        "validate" => return validate(args, out),
+        "" | "help" | "-h" | "--help" => writeln!(out, "{}", USAGE)?,
+        _ => return Err(usage_err!(format!("unknown command: {}", command)).into()),
+    }
+
+    Ok(true)
+}
+
+/// Positional arguments, and `--name value` options in the order given.
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Self {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => options.push((name.to_owned(), args.next().unwrap_or_default())),
+                None => positional.push(arg),
+            }
+        }
+
+        Args {
+            positional,
+            options,
+        }
+    }
+
+    fn file(&self, n: usize) -> io::Result<&str> {
+        self.positional
+            .get(n)
+            .map(String::as_str)
+            .ok_or_else(|| usage_err!("file name not supplied"))
+    }
+
+    fn files(&self) -> io::Result<&[String]> {
+        if self.positional.is_empty() {
+            return Err(usage_err!("file name not supplied"));
+        }
+        Ok(&self.positional)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn options<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.options
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn number(&self, name: &str) -> io::Result<Option<u64>> {
+        self.option(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| usage_err!(format!("--{} is not a number", name)))
+            })
+            .transpose()
+    }
+}
+
+/// A record, with the location of the bytes it was read from.
+struct Entry {
+    offset: u64,
+    length: u64,
+    record: Record<BufferedBody>,
+}
+
+/// A reader which counts the bytes consumed from it.
+struct Counting<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Counting<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.count.set(self.count.get() + amt as u64);
+    }
+}
+
+/// An iterator over the members of a GZIP-compressed file, with their offsets and lengths.
+///
+/// `WarcReader::from_path_gzip` decompresses the members as one stream, which hides where each
+/// member starts in the file. Index entries point at compressed members, so the members are
+/// decompressed one at a time here, and their records are then read with a `WarcReader`.
+struct GzipMembers<R> {
+    reader: Counting<R>,
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for GzipMembers<R> {
+    type Item = io::Result<(u64, u64, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.reader.fill_buf() {
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+            Ok([]) => return None,
+            Ok(_) => {}
+        }
+
+        let offset = self.reader.count.get();
+        let mut data = Vec::new();
+        let decoded = GzipReader::new(&mut self.reader)
+            .and_then(|mut decoder| decoder.read_to_end(&mut data));
+        if let Err(e) = decoded {
+            self.done = true;
+            return Some(Err(e));
+        }
+
+        Some(Ok((offset, self.reader.count.get() - offset, data)))
+    }
+}
+
+/// An iterator over the records of a GZIP-compressed file, decompressed one member at a time.
+struct GzipEntries<R> {
+    members: GzipMembers<R>,
+    pending: VecDeque<std::result::Result<Entry, Error>>,
+}
+
+impl<R: BufRead> Iterator for GzipEntries<R> {
+    type Item = std::result::Result<Entry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.pending.pop_front() {
+                return Some(entry);
+            }
+
+            let (offset, length, data) = match self.members.next()? {
+                Ok(member) => member,
+                Err(e) => return Some(Err(Error::ReadData(e))),
+            };
+            for record in WarcReader::new(data.as_slice()).iter_records() {
+                self.pending.push_back(record.map(|record| Entry {
+                    offset,
+                    length,
+                    record,
+                }));
+            }
+        }
+    }
+}
+
+/// The records of a stream, with their locations.
+type Entries = Box<dyn Iterator<Item = std::result::Result<Entry, Error>>>;
+
+/// Wrap a stream starting at an offset of its file, returning whether it is GZIP-compressed.
+fn counting<R: BufRead>(reader: R, start: u64) -> io::Result<(Counting<R>, bool)> {
+    let mut reader = Counting {
+        inner: reader,
+        count: Rc::new(Cell::new(start)),
+    };
+    let is_gzip = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+
+    Ok((reader, is_gzip))
+}
+
+/// Open a file at an offset, returning a reader over it and whether it is GZIP-compressed.
+fn open_file(path: &str, start: u64) -> io::Result<(Counting<BufReader<fs::File>>, bool)> {
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    counting(BufReader::with_capacity(MB, file), start)
+}
+
+/// Read the records of a stream which starts at an offset of its file.
+fn read_entries<R: BufRead + 'static>(reader: R, start: u64) -> io::Result<Entries> {
+    let (reader, is_gzip) = counting(reader, start)?;
+    if is_gzip {
+        return Ok(Box::new(GzipEntries {
+            members: GzipMembers {
+                reader,
+                done: false,
+            },
+            pending: VecDeque::new(),
+        }));
+    }
+
+    let count = reader.count.clone();
+    let mut records = WarcReader::new(reader).iter_records();
+    Ok(Box::new(std::iter::from_fn(move || {
+        let offset = count.get();
+        let record = records.next()?;
+        Some(record.map(|record| Entry {
+            offset,
+            length: count.get() - offset,
+            record,
+        }))
+    })))
+}
+
+/// Read the records of a file, starting at an offset.
+fn open(path: &str, start: u64) -> io::Result<Entries> {
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    read_entries(BufReader::with_capacity(MB, file), start)
+}
+
+/// Split an HTTP response body into its status code, `Content-Type` and payload.
+fn http_response(record: &Record<BufferedBody>) -> Option<(Option<u16>, Option<String>, &[u8])> {
+    if !record.is_http_response() {
+        return None;
+    }
+
+    let body = record.body();
+    let end = body.windows(4).position(|window| window == b"\r\n\r\n")?;
+    let head = String::from_utf8_lossy(&body[..end]);
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok());
+    let mime = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"))
+        .and_then(|(_, value)| MediaType::parse(value.trim()).ok())
+        .map(|media_type| media_type.essence());
+
+    Some((status, mime, &body[end + 4..]))
+}
+
+fn ls<W: Write>(entries: Entries, out: &mut W) -> Result<()> {
+    for entry in entries {
+        let entry = entry?;
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}",
+            entry.offset,
+            entry.length,
+            entry.record.warc_type(),
+            entry
+                .record
+                .date()
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            entry.record.raw_target_uri().unwrap_or("-")
+        )?;
+    }
+
+    Ok(())
+}
+
+fn cat<W: Write>(args: &Args, out: &mut W) -> Result<()> {
+    let path = args.file(0)?;
+    let start = args.number("offset")?.unwrap_or(0);
+    let entry = select(open(path, start)?, args)?;
+    WarcWriter::new(out).write(&entry.record)?;
+
+    Ok(())
+}
+
+/// Find the record chosen by `--index` or `--id`, or the first one when `--offset` is given.
+fn select(mut entries: Entries, args: &Args) -> Result<Entry> {
+    let entry = if args.number("offset")?.is_some() {
+        entries.next()
+    } else if let Some(index) = args.number("index")? {
+        entries.nth(index as usize)
+    } else if let Some(id) = args.option("id") {
+        entries.find(|entry| match entry {
+            Ok(entry) => entry.record.warc_id() == id,
+            Err(_) => true,
+        })
+    } else {
+        return Err(usage_err!("one of --index, --id or --offset is required").into());
+    };
+
+    Ok(entry.ok_or_else(|| usage_err!("no such record"))??)
+}
+
+fn payload_file_name(record: &Record<BufferedBody>, n: usize) -> String {
+    let name = record
+        .target_uri()
+        .and_then(|url| {
+            url.path_segments()
+                .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+                .map(|segment| {
+                    segment
+                        .chars()
+                        .map(|c| {
+                            if c.is_ascii_alphanumeric() || "._-".contains(c) {
+                                c
+                            } else {
+                                '_'
+                            }
+                        })
+                        .collect::<String>()
+                })
+        })
+        .unwrap_or_else(|| "index".to_owned());
+
+    format!("{:06}-{}", n, name)
+}
+
+fn extract<W: Write>(args: &Args, out: &mut W) -> Result<()> {
+    let dir = PathBuf::from(args.option("dir").unwrap_or("."));
+    fs::create_dir_all(&dir)?;
+
+    for (n, entry) in open(args.file(0)?, 0)?.enumerate() {
+        let record = entry?.record;
+        if !matches!(
+            record.warc_type(),
+            RecordType::Response | RecordType::Resource
+        ) {
+            continue;
+        }
+
+        let path = dir.join(payload_file_name(&record, n));
+        let payload = match http_response(&record) {
+            Some((_, _, payload)) => payload,
+            None => record.body(),
+        };
+        fs::write(&path, payload)?;
+        writeln!(out, "{}", path.display())?;
+    }
+
+    Ok(())
+}
+
+fn filter(args: &Args) -> Result<()> {
+    let input = args.file(0)?;
+    let output = args.file(1)?;
+
+    let file = BufWriter::with_capacity(MB, fs::File::create(output)?);
+    let mut writer = if output.ends_with(".gz") {
+        WarcWriter::with_options(file, WarcWriterOptions::new())
+    } else {
+        WarcWriter::new(file)
+    };
+    let count = filter_records(open(input, 0)?, args, &mut writer)?;
+    writer.into_inner()?.flush()?;
+    eprintln!("{} records written to {}", count, output);
+
+    Ok(())
+}
+
+/// Write the records matching the `--type`, `--uri-prefix`, `--from` and `--to` options, and
+/// return how many were written.
+fn filter_records<W: Write>(
+    entries: Entries,
+    args: &Args,
+    writer: &mut WarcWriter<W>,
+) -> Result<u64> {
+    let types = args
+        .options("type")
+        .map(RecordType::from)
+        .collect::<Vec<_>>();
+    let uri_prefix = args.option("uri-prefix");
+    let start = match args.option("from") {
+        Some(from) => timestamp::parse(from)?,
+        None => DateTime::<Utc>::MIN_UTC,
+    };
+    let end = match args.option("to") {
+        Some(to) => timestamp::parse_end(to)?,
+        None => DateTime::<Utc>::MAX_UTC,
+    };
+    let range = timestamp::DateRange::new(start, end);
+
+    let mut count = 0;
+    for entry in entries {
+        let record = entry?.record;
+        if !types.is_empty() && !types.contains(record.warc_type()) {
+            continue;
+        }
+        if let Some(prefix) = uri_prefix {
+            match record.raw_target_uri() {
+                Some(uri) if uri.starts_with(prefix) => {}
+                _ => continue,
+            }
+        }
+        if !range.contains(record.date()) {
+            continue;
+        }
+
+        writer.write(&record)?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+fn index<W: Write>(args: &Args, out: &mut W) -> Result<()> {
+    let mut lines = Vec::new();
+    for path in args.files()? {
+        let filename = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.clone());
+        lines.extend(index_lines(open(path, 0)?, &filename)?);
+    }
+    lines.sort();
+
+    writeln!(out, " CDX N b a m s k r M S V g")?;
+    for line in lines {
+        writeln!(out, "{}", line)?;
+    }
+
+    Ok(())
+}
+
+/// Return the unsorted CDX lines of the captures in a file.
+fn index_lines(entries: Entries, filename: &str) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let record = &entry.record;
+        let (mime, status) = match record.warc_type() {
+            RecordType::Response => match http_response(record) {
+                Some((status, mime, _)) => (mime, status),
+                None => (record.header(WarcHeader::ContentType).map(Into::into), None),
+            },
+            RecordType::Resource => (record.header(WarcHeader::ContentType).map(Into::into), None),
+            RecordType::Revisit => (Some("warc/revisit".to_owned()), None),
+            _ => continue,
+        };
+        // the key is normalized, but the original URI is listed as it was written
+        let (original, urlkey) = match (record.raw_target_uri(), record.target_uri()) {
+            (Some(raw), Some(url)) => (
+                raw.trim().trim_start_matches('<').trim_end_matches('>'),
+                warc::surt::surt(&url),
+            ),
+            _ => continue,
+        };
+        let digest = record.header(WarcHeader::PayloadDigest).map(|digest| {
+            let digest = digest.to_string();
+            match digest.split_once(':') {
+                Some((_, value)) => value.to_owned(),
+                None => digest,
+            }
+        });
+
+        lines.push(format!(
+            "{} {} {} {} {} {} - - {} {} {}",
+            urlkey,
+            record.timestamp(),
+            original,
+            mime.as_deref().unwrap_or("-"),
+            status.map(|s| s.to_string()).as_deref().unwrap_or("-"),
+            digest.as_deref().unwrap_or("-"),
+            entry.length,
+            entry.offset,
+            filename
+        ));
+    }
+
+    Ok(lines)
+}
+
+fn validate<W: Write>(args: &Args, out: &mut W) -> Result<bool> {
+    let mut failed = false;
+    for path in args.files()? {
+        let mut validator = Validator::new();
+        let (reader, is_gzip) = open_file(path, 0)?;
+        if is_gzip {
+            let mut members = GzipMembers {
+                reader,
+                done: false,
+            };
+            while let Some(member) = members.next() {
+                let (offset, _, data) = match member {
+                    Ok(member) => member,
+                    Err(e) => {
+                        let offset = members.reader.count.get();
+                        validator.check_error(offset, &Error::ReadData(e));
+                        break;
+                    }
+                };
+                for record in WarcSlice::new(&data) {
+                    match record {
+                        Ok(record) => validator.check(offset, &record),
+                        Err(e) => validator.check_error(offset, &e),
+                    };
+                }
+            }
+        } else {
+            drop(reader);
+            // SAFETY: the file is only read, and is assumed not to change while it is validated
+            let mapped = unsafe { MappedWarc::open(path)? };
+            let mut offset = 0;
+            for record in mapped.slice() {
+                match record {
+                    Ok(record) => {
+                        let location = record.location();
+                        validator.check(location.offset, &record);
+                        offset = location.offset + location.length;
+                    }
+                    Err(e) => {
+                        validator.check_error(offset, &e);
+                    }
+                }
+            }
+        }
+
+        let report = validator.finish();
+        for record in report.records() {
+            for issue in record.issues() {
+                writeln!(out, "{}:{}: {}", path, record.offset(), issue)?;
+            }
+        }
+        writeln!(
+            out,
+            "{}: {} records, {} errors, {} warnings",
+            path,
+            report.records().len(),
+            report.errors(),
+            report.warnings()
+        )?;
+        failed |= !report.is_valid();
+    }
+
+    Ok(!failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filter_records, index_lines, ls, read_entries, select, Args, Entries, Entry};
+    use warc::{
+        BufferedBody, Record, RecordBuilder, RecordType, WarcHeader, WarcReader, WarcWriter,
+        WarcWriterOptions,
+    };
+
+    use chrono::prelude::*;
+    use std::io::Cursor;
+
+    fn records() -> Vec<Record<BufferedBody>> {
+        let date = Utc.with_ymd_and_hms(2020, 7, 8, 2, 52, 55).unwrap();
+        let response = RecordBuilder::default()
+            .warc_id("<urn:test:1>")
+            .warc_type(RecordType::Response)
+            .date(date)
+            .header(WarcHeader::TargetURI, "http://example.com/a")
+            .header(WarcHeader::ContentType, "application/http;msgtype=response")
+            .header(WarcHeader::PayloadDigest, "sha1:AAAA")
+            .body(b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\nhi".to_vec())
+            .build()
+            .unwrap();
+        let resource = RecordBuilder::default()
+            .warc_id("<urn:test:2>")
+            .warc_type(RecordType::Resource)
+            .date(date)
+            .header(WarcHeader::TargetURI, "<http://example.com/b.txt>")
+            .header(WarcHeader::ContentType, "text/plain")
+            .body(b"hello".to_vec())
+            .build()
+            .unwrap();
+        let metadata = RecordBuilder::default()
+            .warc_id("<urn:test:3>")
+            .warc_type(RecordType::Metadata)
+            .date(date)
+            .body(b"a: b".to_vec())
+            .build()
+            .unwrap();
+
+        vec![response, resource, metadata]
+    }
+
+    fn serialize(record: &Record<BufferedBody>, options: Option<WarcWriterOptions>) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut writer = match options {
+            Some(options) => WarcWriter::with_options(&mut data, options),
+            None => WarcWriter::new(&mut data),
+        };
+        writer.write(record).unwrap();
+        drop(writer);
+
+        data
+    }
+
+    /// Serialize the records, returning the data and the offset and length of each record.
+    fn fixture(options: Option<WarcWriterOptions>) -> (Vec<u8>, Vec<(usize, usize)>) {
+        let mut data = Vec::new();
+        let mut locations = Vec::new();
+        for record in records() {
+            let serialized = serialize(&record, options.clone());
+            locations.push((data.len(), serialized.len()));
+            data.extend(serialized);
+        }
+
+        (data, locations)
+    }
+
+    fn entries(data: &[u8]) -> Entries {
+        read_entries(Cursor::new(data.to_vec()), 0).unwrap()
+    }
+
+    fn args(args: &[&str]) -> Args {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn ls_records() {
+        for options in [None, Some(WarcWriterOptions::new())] {
+            let (data, locations) = fixture(options);
+            let mut out = Vec::new();
+            ls(entries(&data), &mut out).unwrap();
+
+            let expected = [
+                "response\t2020-07-08T02:52:55Z\thttp://example.com/a",
+                "resource\t2020-07-08T02:52:55Z\t<http://example.com/b.txt>",
+                "metadata\t2020-07-08T02:52:55Z\t-",
+            ]
+            .iter()
+            .zip(locations)
+            .map(|(line, (offset, length))| format!("{}\t{}\t{}\n", offset, length, line))
+            .collect::<String>();
+            assert_eq!(String::from_utf8(out).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn cat_record() {
+        let (data, locations) = fixture(None);
+        let chosen = |entry: Entry| {
+            assert_eq!(entry.record.warc_id(), "<urn:test:2>");
+            assert_eq!(entry.record.body(), b"hello");
+            entry.offset
+        };
+
+        chosen(select(entries(&data), &args(&["--index", "1"])).unwrap());
+        chosen(select(entries(&data), &args(&["--id", "<urn:test:2>"])).unwrap());
+
+        let offset = locations[1].0;
+        let from_offset = read_entries(Cursor::new(data[offset..].to_vec()), offset as u64);
+        let entry = select(from_offset.unwrap(), &args(&["--offset", "0"])).unwrap();
+        assert_eq!(chosen(entry), offset as u64);
+
+        assert!(select(entries(&data), &args(&["--index", "3"])).is_err());
+        assert!(select(entries(&data), &args(&[])).is_err());
+    }
+
+    #[test]
+    fn index_captures() {
+        let (data, locations) = fixture(None);
+        let lines = index_lines(entries(&data), "test.warc").unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                format!(
+                    "com,example)/a 20200708025255 http://example.com/a text/html 200 AAAA - - \
+                     {} {} test.warc",
+                    locations[0].1, locations[0].0
+                ),
+                format!(
+                    "com,example)/b.txt 20200708025255 http://example.com/b.txt text/plain - - \
+                     - - {} {} test.warc",
+                    locations[1].1, locations[1].0
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_by_type() {
+        let (data, _) = fixture(None);
+        let mut output = Vec::new();
+        let mut writer = WarcWriter::new(&mut output);
+        let count = filter_records(
+            entries(&data),
+            &args(&[
+                "--type",
+                "resource",
+                "--type",
+                "metadata",
+                "--uri-prefix",
+                "<",
+            ]),
+            &mut writer,
+        )
+        .unwrap();
+        drop(writer);
+        assert_eq!(count, 1);
+
+        let read = WarcReader::new(output.as_slice())
+            .iter_records()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].warc_id(), "<urn:test:2>");
+
+        let mut writer = WarcWriter::new(Vec::new());
+        assert!(filter_records(entries(&data), &args(&["--from", "20x"]), &mut writer).is_err());
+    }
+
+    #[test]
+    fn bad_input() {
+        let mut out = Vec::new();
+        assert!(ls(entries(b"not a WARC file\r\n\r\n"), &mut out).is_err());
+        assert!(out.is_empty());
+
+        let (data, _) = fixture(Some(WarcWriterOptions::new()));
+        assert!(ls(entries(&data[..data.len() - 10]), &mut out).is_err());
+    }
+}
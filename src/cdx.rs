@@ -0,0 +1,404 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::iter::FromIterator;
+use std::path::Path;
+
+use chrono::prelude::*;
+use nom::{
+    branch::alt,
+    bytes::complete::{escaped_transform, is_not, tag, take},
+    character::complete::{char, multispace0, none_of},
+    combinator::{map, map_opt, value},
+    multi::separated_list0,
+    sequence::{delimited, preceded, separated_pair, tuple},
+    IResult,
+};
+
+use crate::timestamp::{self, DateRange};
+use crate::Error;
+
+/// The fields of a CDX line when the file has no ` CDX` legend line.
+const DEFAULT_LEGEND: &[&str] = &["N", "b", "a", "m", "s", "k", "r", "M", "S", "V", "g"];
+
+/// A single capture listed by a CDX or CDXJ index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CdxRecord {
+    /// The SURT key of the captured URL.
+    pub urlkey: String,
+    /// The capture time, as a timestamp of up to 14 digits.
+    pub timestamp: String,
+    /// The captured URL.
+    pub original: String,
+    /// The media type of the capture, if known.
+    pub mime: Option<String>,
+    /// The HTTP status code of the capture, if known.
+    pub status: Option<u16>,
+    /// The payload digest of the capture, if known.
+    pub digest: Option<String>,
+    /// The length of the record in the WARC file, compressed if the file is, if known.
+    pub length: Option<u64>,
+    /// The offset of the record in the WARC file.
+    pub offset: u64,
+    /// The name of the WARC file holding the record.
+    pub filename: String,
+}
+
+impl CdxRecord {
+    /// Parse a line of a CDXJ index, or of a CDX index with the standard 11 or 9 fields.
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let mut parts = line.splitn(3, ' ');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(urlkey), Some(timestamp), Some(json)) if json.starts_with('{') => {
+                CdxRecord::parse_cdxj(urlkey, timestamp, json)
+            }
+            _ if line.split_whitespace().count() == 9 => {
+                CdxRecord::parse_cdx(line, &["N", "b", "a", "m", "s", "k", "r", "V", "g"])
+            }
+            _ => CdxRecord::parse_cdx(line, DEFAULT_LEGEND),
+        }
+    }
+
+    /// Parse a line of a CDX index, with the field letters given by its legend.
+    pub(crate) fn parse_cdx<S: AsRef<str>>(line: &str, legend: &[S]) -> Result<Self, Error> {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != legend.len() {
+            return Err(Error::ParseIndex(format!(
+                "expected {} fields, found {}",
+                legend.len(),
+                fields.len()
+            )));
+        }
+
+        CdxRecord::from_fields(legend.iter().map(AsRef::as_ref).zip(fields).filter_map(
+            |(letter, field)| {
+                let name = match letter {
+                    "N" => "urlkey",
+                    "b" => "timestamp",
+                    "a" => "url",
+                    "m" => "mime",
+                    "s" => "status",
+                    "k" => "digest",
+                    "S" => "length",
+                    "V" => "offset",
+                    "g" => "filename",
+                    _ => return None,
+                };
+                Some((name, field.to_owned()))
+            },
+        ))
+    }
+
+    fn parse_cdxj(urlkey: &str, timestamp: &str, json: &str) -> Result<Self, Error> {
+        let fields = match json_object(json.trim_end()) {
+            Ok(("", fields)) => fields,
+            _ => return Err(Error::ParseIndex("malformed JSON block".to_string())),
+        };
+
+        CdxRecord::from_fields(
+            vec![
+                ("urlkey", urlkey.to_owned()),
+                ("timestamp", timestamp.to_owned()),
+            ]
+            .into_iter()
+            .chain(
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.clone())),
+            ),
+        )
+    }
+
+    fn from_fields<'a, I>(fields: I) -> Result<Self, Error>
+    where
+        I: Iterator<Item = (&'a str, String)>,
+    {
+        let mut urlkey = None;
+        let mut timestamp = None;
+        let mut original = None;
+        let mut mime = None;
+        let mut status = None;
+        let mut digest = None;
+        let mut length = None;
+        let mut offset = None;
+        let mut filename = None;
+
+        for (name, value) in fields {
+            // CDX files mark missing values with a dash
+            if value == "-" {
+                continue;
+            }
+            match name {
+                "urlkey" => urlkey = Some(value),
+                "timestamp" => timestamp = Some(value),
+                "url" => original = Some(value),
+                "mime" => mime = Some(value),
+                "status" => status = value.parse().ok(),
+                "digest" => digest = Some(value),
+                "length" => length = Some(parse_number(name, &value)?),
+                "offset" => offset = Some(parse_number(name, &value)?),
+                "filename" => filename = Some(value),
+                _ => {}
+            }
+        }
+
+        let missing = |name: &str| Error::ParseIndex(format!("missing field: {}", name));
+        Ok(CdxRecord {
+            urlkey: urlkey.ok_or_else(|| missing("urlkey"))?,
+            timestamp: timestamp.ok_or_else(|| missing("timestamp"))?,
+            original: original.unwrap_or_default(),
+            mime,
+            status,
+            digest,
+            length,
+            offset: offset.ok_or_else(|| missing("offset"))?,
+            filename: filename.ok_or_else(|| missing("filename"))?,
+        })
+    }
+
+    /// Return the capture time, or `None` if the timestamp is malformed.
+    ///
+    /// Timestamps with fewer than 14 digits are taken to be the start of the period they name.
+    pub fn date(&self) -> Option<DateTime<Utc>> {
+        timestamp::parse(&self.timestamp).ok()
+    }
+
+    fn cmp_key(&self, other: &CdxRecord) -> Ordering {
+        self.urlkey
+            .cmp(&other.urlkey)
+            .then_with(|| self.timestamp.cmp(&other.timestamp))
+    }
+}
+
+fn parse_number(name: &str, value: &str) -> Result<u64, Error> {
+    value
+        .parse()
+        .map_err(|_| Error::ParseIndex(format!("malformed {}: {}", name, value)))
+}
+
+fn json_string(input: &str) -> IResult<&str, String> {
+    let escape = alt((
+        value('"', char('"')),
+        value('\\', char('\\')),
+        value('/', char('/')),
+        value('\n', char('n')),
+        value('\r', char('r')),
+        value('\t', char('t')),
+        map_opt(preceded(char('u'), take(4usize)), |hex: &str| {
+            u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+        }),
+    ));
+    alt((
+        delimited(
+            char('"'),
+            escaped_transform(none_of("\\\""), '\\', escape),
+            char('"'),
+        ),
+        map(tag("\"\""), |_| String::new()),
+    ))(input)
+}
+
+fn json_value(input: &str) -> IResult<&str, String> {
+    alt((
+        json_string,
+        map(is_not(",}"), |literal: &str| literal.trim().to_owned()),
+    ))(input)
+}
+
+/// Parse a flat JSON object, as found in CDXJ lines, with all values as strings.
+fn json_object(input: &str) -> IResult<&str, Vec<(String, String)>> {
+    delimited(
+        tuple((char('{'), multispace0)),
+        separated_list0(
+            tuple((multispace0, char(','), multispace0)),
+            separated_pair(
+                json_string,
+                tuple((multispace0, char(':'), multispace0)),
+                json_value,
+            ),
+        ),
+        tuple((multispace0, char('}'))),
+    )(input)
+}
+
+/// A CDX or CDXJ index, held in memory and sorted by SURT key and timestamp.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CdxIndex {
+    records: Vec<CdxRecord>,
+}
+
+impl CdxIndex {
+    /// Read an index from a stream of CDX or CDXJ lines.
+    ///
+    /// A ` CDX` legend line sets the fields of the CDX lines which follow it. Blank lines and
+    /// lines starting with `!` are ignored.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut legend: Option<Vec<String>> = None;
+        let mut records = Vec::new();
+        for line in reader.lines() {
+            let line = line.map_err(Error::ReadData)?;
+            if let Some(fields) = line.trim_start().strip_prefix("CDX ") {
+                legend = Some(fields.split_whitespace().map(str::to_owned).collect());
+                continue;
+            }
+            if line.trim().is_empty() || line.starts_with('!') {
+                continue;
+            }
+
+            let record = match legend {
+                Some(ref legend) if !line.contains(" {") => CdxRecord::parse_cdx(&line, legend)?,
+                _ => CdxRecord::parse(&line)?,
+            };
+            records.push(record);
+        }
+
+        Ok(records.into_iter().collect())
+    }
+
+    /// Read an index from a CDX or CDXJ file.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = fs::File::open(path).map_err(Error::ReadData)?;
+
+        CdxIndex::from_reader(BufReader::new(file))
+    }
+
+    /// Return all records, sorted by SURT key and timestamp.
+    pub fn records(&self) -> &[CdxRecord] {
+        &self.records
+    }
+
+    /// Return the records with exactly the given SURT key, oldest first.
+    pub fn lookup(&self, urlkey: &str) -> &[CdxRecord] {
+        let start = self
+            .records
+            .partition_point(|record| record.urlkey.as_str() < urlkey);
+        let end = start
+            + self.records[start..].partition_point(|record| record.urlkey.as_str() == urlkey);
+
+        &self.records[start..end]
+    }
+
+    /// Return the records whose SURT key starts with the given prefix.
+    pub fn lookup_prefix(&self, prefix: &str) -> &[CdxRecord] {
+        let start = self
+            .records
+            .partition_point(|record| record.urlkey.as_str() < prefix);
+        let end = start
+            + self.records[start..].partition_point(|record| record.urlkey.starts_with(prefix));
+
+        &self.records[start..end]
+    }
+
+    /// Return the records with the given SURT key captured within a range of dates, oldest first.
+    pub fn lookup_between<'a>(
+        &'a self,
+        urlkey: &str,
+        range: &'a DateRange,
+    ) -> impl Iterator<Item = &'a CdxRecord> + 'a {
+        self.lookup(urlkey).iter().filter(move |record| {
+            record
+                .date()
+                .map(|date| range.contains(&date))
+                .unwrap_or(false)
+        })
+    }
+
+    /// Return the record with the given SURT key captured closest to the given time.
+    ///
+    /// Of two captures equally distant, the earlier one is returned.
+    pub fn closest(&self, urlkey: &str, date: &DateTime<Utc>) -> Option<&CdxRecord> {
+        self.lookup(urlkey)
+            .iter()
+            .filter_map(|record| record.date().map(|d| (record, (d - *date).num_seconds())))
+            .min_by_key(|(_, distance)| (distance.abs(), *distance))
+            .map(|(record, _)| record)
+    }
+}
+
+impl FromIterator<CdxRecord> for CdxIndex {
+    fn from_iter<I: IntoIterator<Item = CdxRecord>>(iter: I) -> Self {
+        let mut index = CdxIndex::default();
+        index.extend(iter);
+
+        index
+    }
+}
+
+impl Extend<CdxRecord> for CdxIndex {
+    fn extend<I: IntoIterator<Item = CdxRecord>>(&mut self, iter: I) {
+        self.records.extend(iter);
+        self.records.sort_by(CdxRecord::cmp_key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CdxIndex, CdxRecord};
+
+    use chrono::prelude::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn parse_lines() {
+        let cdx = CdxRecord::parse(
+            "com,example)/ 20200708025255 http://example.com/ text/html 200 \
+             SHA1DIGEST - - 1043 333 example.warc.gz",
+        )
+        .unwrap();
+        let cdxj = CdxRecord::parse(
+            r#"com,example)/ 20200708025255 {"url": "http://example.com/", "mime": "text/html", "status": "200", "digest": "SHA1DIGEST", "length": "1043", "offset": 333, "filename": "example.warc.gz", "note": "\"quoted\" é"}"#,
+        )
+        .unwrap();
+        assert_eq!(cdx, cdxj);
+        assert_eq!(cdx.status, Some(200));
+        assert_eq!(cdx.length, Some(1043));
+        assert_eq!(
+            cdx.date(),
+            Some(Utc.with_ymd_and_hms(2020, 7, 8, 2, 52, 55).unwrap())
+        );
+
+        let cdx9 =
+            CdxRecord::parse("com,example)/ 2020 http://example.com/ - - - - 333 example.warc.gz")
+                .unwrap();
+        assert_eq!(cdx9.mime, None);
+        assert_eq!(cdx9.length, None);
+        assert_eq!(
+            cdx9.date(),
+            Some(Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap())
+        );
+
+        assert!(CdxRecord::parse("com,example)/ 2020 {\"url\": }").is_err());
+        assert!(CdxRecord::parse("com,example)/ 2020 http://example.com/").is_err());
+    }
+
+    #[test]
+    fn lookup() {
+        let raw = "\
+             CDX N b a V g\n\
+            org,example)/b 20200101000000 http://example.org/b 10 a.warc\n\
+            com,example)/ 20200301000000 http://example.com/ 30 a.warc\n\
+            com,example)/ 20200101000000 http://example.com/ 0 a.warc\n\
+            \n\
+            com,example)/a 20200201000000 http://example.com/a 20 a.warc\n\
+        ";
+        let index = CdxIndex::from_reader(Cursor::new(raw)).unwrap();
+        assert_eq!(index.records().len(), 4);
+
+        let offsets = |records: &[CdxRecord]| records.iter().map(|r| r.offset).collect::<Vec<_>>();
+        assert_eq!(offsets(index.lookup("com,example)/")), [0, 30]);
+        assert_eq!(offsets(index.lookup_prefix("com,example)/")), [0, 30, 20]);
+        assert!(index.lookup("com,example)/c").is_empty());
+        assert!(index.lookup_prefix("net,").is_empty());
+
+        let date = |m, d| Utc.with_ymd_and_hms(2020, m, d, 0, 0, 0).unwrap();
+        assert_eq!(
+            index.closest("com,example)/", &date(1, 31)).unwrap().offset,
+            0
+        );
+        assert_eq!(
+            index.closest("com,example)/", &date(2, 1)).unwrap().offset,
+            30
+        );
+        assert!(index.closest("com,example)/c", &date(2, 1)).is_none());
+    }
+}
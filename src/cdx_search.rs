@@ -0,0 +1,415 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::{CdxRecord, Error};
+
+/// A CDX or CDXJ file sorted by line, searched in place by binary search.
+///
+/// Only the lines needed to find the matches are read, so the file does not need to fit in
+/// memory. The file must be sorted by byte value, as `LC_ALL=C sort` does.
+pub struct SortedCdxFile<R> {
+    reader: BufReader<R>,
+    len: u64,
+    legend: Option<Vec<String>>,
+}
+
+impl<R: Read + Seek> SortedCdxFile<R> {
+    /// Create a searcher over a sorted index.
+    ///
+    /// A ` CDX` legend line, which sorts first, sets the fields of the CDX lines.
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let mut reader = BufReader::with_capacity(8192, reader);
+        let len = reader.seek(SeekFrom::End(0)).map_err(Error::ReadData)?;
+        reader.seek(SeekFrom::Start(0)).map_err(Error::ReadData)?;
+
+        let mut first = Vec::new();
+        reader
+            .read_until(b'\n', &mut first)
+            .map_err(Error::ReadData)?;
+        let first = String::from_utf8_lossy(&first);
+        let legend = first
+            .trim_start()
+            .strip_prefix("CDX ")
+            .map(|fields| fields.split_whitespace().map(str::to_owned).collect());
+
+        Ok(SortedCdxFile {
+            reader,
+            len,
+            legend,
+        })
+    }
+
+    /// Read the first whole line starting at or after a position, returning `false` at the end.
+    ///
+    /// Lines are read as bytes, since the position may fall inside a multi-byte character.
+    fn line_after(&mut self, position: u64, line: &mut Vec<u8>) -> Result<bool, Error> {
+        line.clear();
+        if position == 0 {
+            self.reader.seek(SeekFrom::Start(0))
+        } else {
+            self.reader.seek(SeekFrom::Start(position - 1))
+        }
+        .map_err(Error::ReadData)?;
+        if position > 0 {
+            // skip the rest of the line the position falls in
+            self.reader
+                .read_until(b'\n', line)
+                .map_err(Error::ReadData)?;
+            line.clear();
+        }
+
+        let read = self
+            .reader
+            .read_until(b'\n', line)
+            .map_err(Error::ReadData)?;
+        Ok(read > 0)
+    }
+
+    /// Return all lines starting with the given prefix, in order.
+    pub fn search(&mut self, prefix: &str) -> Result<Vec<String>, Error> {
+        let prefix = prefix.as_bytes();
+        let mut line = Vec::new();
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.line_after(middle, &mut line)? && line.as_slice() < prefix {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+
+        let mut matches = Vec::new();
+        let mut more = self.line_after(low, &mut line)?;
+        while more {
+            let mut trimmed = line.as_slice();
+            while let [rest @ .., b'\r' | b'\n'] = trimmed {
+                trimmed = rest;
+            }
+            if trimmed.starts_with(prefix) {
+                let matched = std::str::from_utf8(trimmed)
+                    .map_err(|_| Error::ParseIndex("line is not valid UTF-8".to_owned()))?;
+                matches.push(matched.to_owned());
+            } else if trimmed > prefix {
+                break;
+            }
+            line.clear();
+            more = self
+                .reader
+                .read_until(b'\n', &mut line)
+                .map_err(Error::ReadData)?
+                > 0;
+        }
+
+        Ok(matches)
+    }
+
+    /// Return the records with exactly the given SURT key, oldest first.
+    pub fn lookup(&mut self, urlkey: &str) -> Result<Vec<CdxRecord>, Error> {
+        let lines = self.search(&format!("{} ", urlkey))?;
+        parse_lines(&lines, self.legend.as_deref())
+    }
+
+    /// Return the records whose SURT key starts with the given prefix.
+    pub fn lookup_prefix(&mut self, prefix: &str) -> Result<Vec<CdxRecord>, Error> {
+        let lines = self.search(prefix)?;
+        parse_lines(&lines, self.legend.as_deref())
+    }
+}
+
+impl SortedCdxFile<fs::File> {
+    /// Create a searcher over a sorted index file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        SortedCdxFile::new(fs::File::open(path).map_err(Error::ReadData)?)
+    }
+}
+
+fn parse_lines(lines: &[String], legend: Option<&[String]>) -> Result<Vec<CdxRecord>, Error> {
+    lines
+        .iter()
+        .map(|line| match legend {
+            Some(legend) if !line.contains(" {") => CdxRecord::parse_cdx(line, legend),
+            _ => CdxRecord::parse(line),
+        })
+        .collect()
+}
+
+/// A block of a ZipNum index, as listed by its summary.
+#[cfg(feature = "gzip")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ZipNumBlock {
+    key: String,
+    shard: String,
+    offset: u64,
+    length: u64,
+}
+
+/// A ZipNum index: a sorted CDX or CDXJ index compressed in blocks of lines, with a summary of
+/// the first key of every block.
+///
+/// The summary is held in memory and binary searched, and only the blocks which may hold matches
+/// are read and decompressed. Summary lines hold a key, a shard name, and the offset and length
+/// of the block within the shard, separated by tabs. Shards are found using a location file, if
+/// loaded, or else next to the summary, with or without a `.gz` extension.
+#[cfg(feature = "gzip")]
+pub struct ZipNumIndex {
+    blocks: Vec<ZipNumBlock>,
+    root: std::path::PathBuf,
+    locations: std::collections::HashMap<String, std::path::PathBuf>,
+}
+
+#[cfg(feature = "gzip")]
+impl ZipNumIndex {
+    /// Open a ZipNum index by its summary file.
+    pub fn open<P: AsRef<Path>>(summary_path: P) -> Result<Self, Error> {
+        let summary_path = summary_path.as_ref();
+        let file = fs::File::open(summary_path).map_err(Error::ReadData)?;
+
+        let mut blocks = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(Error::ReadData)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() < 4 {
+                return Err(Error::ParseIndex(format!(
+                    "expected at least 4 summary fields, found {}",
+                    fields.len()
+                )));
+            }
+            let number = |value: &str| {
+                value
+                    .parse()
+                    .map_err(|_| Error::ParseIndex(format!("malformed summary number: {}", value)))
+            };
+            blocks.push(ZipNumBlock {
+                key: fields[0].to_owned(),
+                shard: fields[1].to_owned(),
+                offset: number(fields[2])?,
+                length: number(fields[3])?,
+            });
+        }
+
+        Ok(ZipNumIndex {
+            blocks,
+            root: summary_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            locations: Default::default(),
+        })
+    }
+
+    /// Load a location file, which maps shard names to paths, separated by a tab.
+    pub fn load_locations<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let file = fs::File::open(path).map_err(Error::ReadData)?;
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(Error::ReadData)?;
+            let mut fields = line.splitn(2, '\t');
+            if let (Some(shard), Some(location)) = (fields.next(), fields.next()) {
+                self.locations
+                    .insert(shard.to_owned(), self.root.join(location.trim()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_block(&self, block: &ZipNumBlock) -> Result<String, Error> {
+        let path = match self.locations.get(&block.shard) {
+            Some(path) => path.clone(),
+            None => {
+                let path = self.root.join(&block.shard);
+                if path.exists() {
+                    path
+                } else {
+                    self.root.join(format!("{}.gz", block.shard))
+                }
+            }
+        };
+
+        let mut file = fs::File::open(path).map_err(Error::ReadData)?;
+        file.seek(SeekFrom::Start(block.offset))
+            .map_err(Error::ReadData)?;
+        let mut decoder =
+            libflate::gzip::MultiDecoder::new(file.take(block.length)).map_err(Error::ReadData)?;
+        let mut lines = String::new();
+        decoder
+            .read_to_string(&mut lines)
+            .map_err(Error::ReadData)?;
+
+        Ok(lines)
+    }
+
+    /// Return all lines starting with the given prefix, in order.
+    pub fn search(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        // the block before the first one at or past the prefix may hold matches too
+        let first = self
+            .blocks
+            .partition_point(|block| block.key.as_str() < prefix)
+            .saturating_sub(1);
+
+        let mut matches = Vec::new();
+        for block in self.blocks[first..].iter() {
+            if block.key.as_str() > prefix && !block.key.starts_with(prefix) {
+                break;
+            }
+            let lines = self.read_block(block)?;
+            matches.extend(
+                lines
+                    .lines()
+                    .filter(|line| line.starts_with(prefix))
+                    .map(str::to_owned),
+            );
+        }
+
+        Ok(matches)
+    }
+
+    /// Return the records with exactly the given SURT key, oldest first.
+    pub fn lookup(&self, urlkey: &str) -> Result<Vec<CdxRecord>, Error> {
+        parse_lines(&self.search(&format!("{} ", urlkey))?, None)
+    }
+
+    /// Return the records whose SURT key starts with the given prefix.
+    pub fn lookup_prefix(&self, prefix: &str) -> Result<Vec<CdxRecord>, Error> {
+        parse_lines(&self.search(prefix)?, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SortedCdxFile;
+
+    use std::io::Cursor;
+
+    fn lines() -> Vec<String> {
+        let mut lines = vec![" CDX N b a V g".to_owned()];
+        for host in ["com,example", "com,example,www", "org,example"] {
+            for path in ["/", "/a", "/b"] {
+                for month in 1..=3 {
+                    lines.push(format!(
+                        "{}){} 20200{}01000000 http://example/ {} f.warc",
+                        host,
+                        path,
+                        month,
+                        lines.len()
+                    ));
+                }
+            }
+        }
+
+        lines
+    }
+
+    #[test]
+    fn binary_search() {
+        let data = lines().join("\n") + "\n";
+        let mut file = SortedCdxFile::new(Cursor::new(data)).unwrap();
+
+        let records = file.lookup("com,example)/").unwrap();
+        assert_eq!(
+            records.iter().map(|r| r.offset).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert_eq!(file.lookup_prefix("com,example)").unwrap().len(), 9);
+        assert_eq!(file.lookup_prefix("com,example").unwrap().len(), 18);
+        assert_eq!(file.lookup_prefix("org,example)/b").unwrap().len(), 3);
+        assert_eq!(file.search("org,example)/b 202003").unwrap().len(), 1);
+        assert!(file.lookup("com,example)/c").unwrap().is_empty());
+        assert!(file.lookup("aaa").unwrap().is_empty());
+        assert!(file.lookup("zzz").unwrap().is_empty());
+
+        let mut empty = SortedCdxFile::new(Cursor::new("")).unwrap();
+        assert!(empty.search("com").unwrap().is_empty());
+    }
+
+    #[test]
+    fn non_ascii_lines() {
+        let mut lines = Vec::new();
+        for (i, path) in ["/caf\u{e9}", "/\u{65e5}\u{672c}", "/\u{1f600}\u{1f600}"]
+            .iter()
+            .enumerate()
+        {
+            for month in 1..=3 {
+                lines.push(format!(
+                    "com,example){} 20200{}01000000 http://example/ {} f.warc",
+                    path,
+                    month,
+                    i * 3 + month
+                ));
+            }
+        }
+        lines.sort();
+        lines.insert(0, " CDX N b a V g".to_owned());
+        let data = lines.join("\n") + "\n";
+
+        // a search may start from any position, including ones inside characters
+        let mut file = SortedCdxFile::new(Cursor::new(data.clone())).unwrap();
+        let mut line = Vec::new();
+        for position in 0..data.len() as u64 {
+            assert!(file.line_after(position, &mut line).is_ok());
+        }
+
+        for path in ["/caf\u{e9}", "/\u{65e5}\u{672c}", "/\u{1f600}\u{1f600}"] {
+            let mut file = SortedCdxFile::new(Cursor::new(data.clone())).unwrap();
+            let records = file.lookup(&format!("com,example){}", path)).unwrap();
+            assert_eq!(records.len(), 3);
+            assert!(records.iter().all(|r| r.urlkey.ends_with(path)));
+        }
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn zipnum() {
+        use super::ZipNumIndex;
+        use crate::WarcWriterOptions;
+        use std::fs;
+
+        let root = std::env::temp_dir().join(format!("zipnum-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&root).unwrap();
+
+        // blocks of four lines, each compressed on its own
+        let lines = lines();
+        let mut shard = Vec::new();
+        let mut summary = String::new();
+        for block in lines[1..].chunks(4) {
+            let member = WarcWriterOptions::new()
+                .compress_member((block.join("\n") + "\n").as_bytes())
+                .unwrap();
+            let key = block[0]
+                .splitn(3, ' ')
+                .take(2)
+                .collect::<Vec<_>>()
+                .join(" ");
+            summary.push_str(&format!(
+                "{}\tshard-0\t{}\t{}\n",
+                key,
+                shard.len(),
+                member.len()
+            ));
+            shard.extend(member);
+        }
+        fs::write(root.join("shard-0.gz"), shard).unwrap();
+        fs::write(root.join("index.summary"), summary).unwrap();
+
+        let index = ZipNumIndex::open(root.join("index.summary")).unwrap();
+        let legend = ["N", "b", "a", "V", "g"];
+        let parse = |lines: Vec<String>| {
+            lines
+                .iter()
+                .map(|line| crate::CdxRecord::parse_cdx(line, &legend).unwrap().offset)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(parse(index.search("com,example)/ ").unwrap()), [1, 2, 3]);
+        assert_eq!(parse(index.search("com,example)/b").unwrap()), [7, 8, 9]);
+        assert_eq!(index.search("com,example,www)").unwrap().len(), 9);
+        assert_eq!(index.search("org,example)/b 202003").unwrap().len(), 1);
+        assert!(index.search("net,").unwrap().is_empty());
+        assert!(index.search("zzz").unwrap().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,210 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use url::Url;
+
+use crate::surt::surt;
+use crate::timestamp::DateRange;
+use crate::{BufferedBody, CdxIndex, CdxRecord, Error, Record, WarcReader};
+
+/// A collection of WARC files in a directory, with a CDX or CDXJ index of their records.
+///
+/// Captures are looked up in the index by URL and time, and their records are read from the WARC
+/// files by seeking to the offset the index gives, without scanning the files. Files may be
+/// uncompressed, or compressed with one GZIP member per record.
+pub struct Collection {
+    index: CdxIndex,
+    root: PathBuf,
+}
+
+impl Collection {
+    /// Create a collection from an index, and the directory its file names are relative to.
+    pub fn new<P: Into<PathBuf>>(index: CdxIndex, root: P) -> Self {
+        Collection {
+            index,
+            root: root.into(),
+        }
+    }
+
+    /// Create a collection from an index file, and the directory its file names are relative to.
+    pub fn open<P: AsRef<Path>, Q: Into<PathBuf>>(index_path: P, root: Q) -> Result<Self, Error> {
+        Ok(Collection::new(CdxIndex::from_path(index_path)?, root))
+    }
+
+    /// Return the index of this collection.
+    pub fn index(&self) -> &CdxIndex {
+        &self.index
+    }
+
+    /// Return the index of this collection, to add more records to it.
+    pub fn index_mut(&mut self) -> &mut CdxIndex {
+        &mut self.index
+    }
+
+    /// Return all captures of a URL, oldest first.
+    pub fn captures(&self, url: &Url) -> &[CdxRecord] {
+        self.index.lookup(&surt(url))
+    }
+
+    /// Return all captures of URLs starting with the given URL, sorted by SURT key and time.
+    pub fn captures_prefix(&self, url: &Url) -> &[CdxRecord] {
+        self.index.lookup_prefix(&surt(url))
+    }
+
+    /// Return the captures of a URL within a range of dates, oldest first.
+    pub fn captures_between<'a>(
+        &'a self,
+        url: &Url,
+        range: &'a DateRange,
+    ) -> impl Iterator<Item = &'a CdxRecord> + 'a {
+        self.index.lookup_between(&surt(url), range)
+    }
+
+    /// Return the capture of a URL closest to the given time.
+    pub fn closest(&self, url: &Url, date: &DateTime<Utc>) -> Option<&CdxRecord> {
+        self.index.closest(&surt(url), date)
+    }
+
+    /// Read the record of a capture from its WARC file.
+    pub fn fetch(&self, capture: &CdxRecord) -> Result<Record<BufferedBody>, Error> {
+        let mut file =
+            fs::File::open(self.root.join(&capture.filename)).map_err(Error::ReadData)?;
+        file.seek(SeekFrom::Start(capture.offset))
+            .map_err(Error::ReadData)?;
+        let mut reader = BufReader::new(file);
+
+        let is_gzip = reader
+            .fill_buf()
+            .map_err(Error::ReadData)?
+            .starts_with(&[0x1f, 0x8b]);
+        if is_gzip {
+            return fetch_gzip(reader);
+        }
+
+        WarcReader::new(reader)
+            .iter_records()
+            .next()
+            .unwrap_or(Err(Error::UnexpectedEOB))
+    }
+
+    /// Read the record of the capture of a URL closest to the given time, if there is one.
+    pub fn get(
+        &self,
+        url: &Url,
+        date: &DateTime<Utc>,
+    ) -> Result<Option<Record<BufferedBody>>, Error> {
+        self.closest(url, date)
+            .map(|capture| self.fetch(capture))
+            .transpose()
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn fetch_gzip<R: BufRead>(reader: R) -> Result<Record<BufferedBody>, Error> {
+    use std::io::Read;
+
+    // only the member holding the record is decompressed
+    let mut decoder = libflate::gzip::Decoder::new(reader).map_err(Error::ReadData)?;
+    let mut data = Vec::new();
+    decoder.read_to_end(&mut data).map_err(Error::ReadData)?;
+
+    crate::WarcSlice::new(&data).record_at(0)?.to_record()
+}
+
+#[cfg(not(feature = "gzip"))]
+fn fetch_gzip<R: BufRead>(_: R) -> Result<Record<BufferedBody>, Error> {
+    Err(Error::ReadData(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "GZIP support is not enabled",
+    )))
+}
+
+#[cfg(all(test, feature = "gzip"))]
+mod tests {
+    use super::Collection;
+    use crate::timestamp::DateRange;
+    use crate::{CdxIndex, RecordBuilder, RecordType, WarcHeader, WarcWriter, WarcWriterOptions};
+
+    use chrono::prelude::*;
+    use std::fs;
+    use std::io::Cursor;
+    use url::Url;
+
+    #[test]
+    fn lookup_and_fetch() {
+        let root = std::env::temp_dir().join(format!("collection-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&root).unwrap();
+
+        let date = |m| Utc.with_ymd_and_hms(2020, m, 1, 0, 0, 0).unwrap();
+        let captures = [
+            ("http://example.com/", date(1)),
+            ("http://example.com/a", date(2)),
+            ("http://example.com/", date(3)),
+        ];
+
+        let mut cdx = String::from(" CDX N b a m s k r M S V g\n");
+        for (gzip, filename) in [(true, "a.warc.gz"), (false, "b.warc")] {
+            let mut data = Vec::new();
+            for (url, date) in captures.iter() {
+                let record = RecordBuilder::default()
+                    .warc_type(RecordType::Response)
+                    .date(*date)
+                    .header(WarcHeader::TargetURI, *url)
+                    .body(format!("{} at {}", url, date).into_bytes())
+                    .build()
+                    .unwrap();
+
+                let offset = data.len();
+                if gzip {
+                    let options = WarcWriterOptions::new();
+                    WarcWriter::with_options(&mut data, options)
+                        .write(&record)
+                        .unwrap();
+                } else {
+                    WarcWriter::new(&mut data).write(&record).unwrap();
+                }
+                cdx.push_str(&format!(
+                    "{} {} {} - - - - - {} {} {}\n",
+                    crate::surt::surt(&Url::parse(url).unwrap()),
+                    date.format("%Y%m%d%H%M%S"),
+                    url,
+                    data.len() - offset,
+                    offset,
+                    filename
+                ));
+            }
+            fs::write(root.join(filename), data).unwrap();
+        }
+
+        let index = CdxIndex::from_reader(Cursor::new(cdx)).unwrap();
+        let collection = Collection::new(index, &root);
+
+        let url = Url::parse("http://example.com/").unwrap();
+        assert_eq!(collection.captures(&url).len(), 4);
+        assert_eq!(collection.captures_prefix(&url).len(), 6);
+
+        for capture in collection.captures_prefix(&url) {
+            let record = collection.fetch(capture).unwrap();
+            assert_eq!(
+                record.header(WarcHeader::TargetURI).unwrap(),
+                capture.original
+            );
+            assert_eq!(
+                record.date().format("%Y%m%d%H%M%S").to_string(),
+                capture.timestamp
+            );
+        }
+
+        let range = DateRange::from_timestamps("202002", "2020").unwrap();
+        assert_eq!(collection.captures_between(&url, &range).count(), 2);
+
+        let record = collection.get(&url, &date(4)).unwrap().unwrap();
+        assert_eq!(record.date(), &date(3));
+        let missing = Url::parse("http://example.com/missing").unwrap();
+        assert!(collection.get(&missing, &date(4)).unwrap().is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,404 @@
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+use arrow_array::builder::{
+    StringBuilder, TimestampMillisecondBuilder, UInt16Builder, UInt64Builder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::DateTime;
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+
+use crate::export::{MetadataIter, RecordMetadata};
+use crate::{Error, WarcHeader, WarcReader};
+
+/// The headers defined by the standard, in the order of their columns.
+const HEADERS: [WarcHeader; 19] = [
+    WarcHeader::ContentLength,
+    WarcHeader::ContentType,
+    WarcHeader::BlockDigest,
+    WarcHeader::ConcurrentTo,
+    WarcHeader::Date,
+    WarcHeader::Filename,
+    WarcHeader::IdentifiedPayloadType,
+    WarcHeader::IPAddress,
+    WarcHeader::PayloadDigest,
+    WarcHeader::Profile,
+    WarcHeader::RecordID,
+    WarcHeader::RefersTo,
+    WarcHeader::SegmentNumber,
+    WarcHeader::SegmentOriginID,
+    WarcHeader::SegmentTotalLength,
+    WarcHeader::TargetURI,
+    WarcHeader::Truncated,
+    WarcHeader::WarcType,
+    WarcHeader::WarcInfoID,
+];
+
+fn header_field(header: &WarcHeader) -> Field {
+    let data_type = match header {
+        WarcHeader::ContentLength | WarcHeader::SegmentNumber | WarcHeader::SegmentTotalLength => {
+            DataType::UInt64
+        }
+        WarcHeader::Date => DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+        _ => DataType::Utf8,
+    };
+    Field::new(header.to_string().replace('-', "_"), data_type, true)
+}
+
+/// Return the schema of the batches returned by a `RecordBatchIter`.
+///
+/// The columns are:
+/// * `offset` and `length`: the location of the record in the stream read
+/// * a column for each header defined by the standard, named as the header in lower case with
+///   underscores for hyphens, such as `warc_target_uri`
+/// * `http_status`, `http_content_type` and `http_location`: fields of the HTTP message held by
+///   the record, if there is one
+///
+/// `content_length`, `warc_segment_number` and `warc_segment_total_length` are integers, and
+/// `warc_date` is a timestamp in milliseconds. All other headers are strings. Every header column
+/// is nullable, even for headers the standard requires, since records are not validated.
+pub fn record_batch_schema() -> SchemaRef {
+    let mut fields = vec![
+        Field::new("offset", DataType::UInt64, false),
+        Field::new("length", DataType::UInt64, false),
+    ];
+    fields.extend(HEADERS.iter().map(header_field));
+    fields.push(Field::new("http_status", DataType::UInt16, true));
+    fields.push(Field::new("http_content_type", DataType::Utf8, true));
+    fields.push(Field::new("http_location", DataType::Utf8, true));
+
+    Arc::new(Schema::new(fields))
+}
+
+/// A builder for a column of header values.
+enum HeaderColumn {
+    UInt64(UInt64Builder),
+    Timestamp(TimestampMillisecondBuilder),
+    Utf8(StringBuilder),
+}
+
+impl HeaderColumn {
+    fn new(field: &Field) -> Self {
+        match field.data_type() {
+            DataType::UInt64 => HeaderColumn::UInt64(UInt64Builder::new()),
+            DataType::Timestamp(_, _) => {
+                HeaderColumn::Timestamp(TimestampMillisecondBuilder::new().with_timezone("UTC"))
+            }
+            _ => HeaderColumn::Utf8(StringBuilder::new()),
+        }
+    }
+
+    /// Append a value, or a null if it is missing or cannot be parsed.
+    fn append(&mut self, value: Option<&str>) {
+        match self {
+            HeaderColumn::UInt64(builder) => {
+                builder.append_option(value.and_then(|v| v.trim().parse().ok()))
+            }
+            HeaderColumn::Timestamp(builder) => builder.append_option(
+                value
+                    .and_then(|v| DateTime::parse_from_rfc3339(v.trim()).ok())
+                    .map(|date| date.timestamp_millis()),
+            ),
+            HeaderColumn::Utf8(builder) => builder.append_option(value),
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            HeaderColumn::UInt64(builder) => Arc::new(builder.finish()),
+            HeaderColumn::Timestamp(builder) => Arc::new(builder.finish()),
+            HeaderColumn::Utf8(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+/// The builders of the columns of a record batch.
+struct Columns {
+    offset: UInt64Builder,
+    length: UInt64Builder,
+    headers: Vec<HeaderColumn>,
+    http_status: UInt16Builder,
+    http_content_type: StringBuilder,
+    http_location: StringBuilder,
+}
+
+impl Columns {
+    fn new() -> Self {
+        Columns {
+            offset: UInt64Builder::new(),
+            length: UInt64Builder::new(),
+            headers: HEADERS
+                .iter()
+                .map(|header| HeaderColumn::new(&header_field(header)))
+                .collect(),
+            http_status: UInt16Builder::new(),
+            http_content_type: StringBuilder::new(),
+            http_location: StringBuilder::new(),
+        }
+    }
+
+    fn append(&mut self, metadata: &RecordMetadata) {
+        self.offset.append_value(metadata.location.offset);
+        self.length.append_value(metadata.location.length);
+        for (header, column) in HEADERS.iter().zip(self.headers.iter_mut()) {
+            let value = metadata.headers.as_ref().get(header);
+            column.append(value.and_then(|v| std::str::from_utf8(v).ok()));
+        }
+
+        let http = metadata.http.as_ref();
+        self.http_status
+            .append_option(http.and_then(|http| http.status));
+        self.http_content_type
+            .append_option(http.and_then(|http| http.headers.get("content-type")));
+        self.http_location
+            .append_option(http.and_then(|http| http.headers.get("location")));
+    }
+
+    fn finish(&mut self, schema: SchemaRef) -> RecordBatch {
+        let mut arrays: Vec<ArrayRef> = vec![
+            Arc::new(self.offset.finish()),
+            Arc::new(self.length.finish()),
+        ];
+        arrays.extend(self.headers.iter_mut().map(HeaderColumn::finish));
+        arrays.push(Arc::new(self.http_status.finish()));
+        arrays.push(Arc::new(self.http_content_type.finish()));
+        arrays.push(Arc::new(self.http_location.finish()));
+
+        // NB: every column is built from the schema's own fields, and all of them are nullable
+        RecordBatch::try_new(schema, arrays).expect("columns match the schema")
+    }
+}
+
+/// An iterator over Arrow record batches of the metadata of the records read by a reader.
+///
+/// Each row holds the headers and location of a record, and fields of the HTTP message it holds.
+/// See `record_batch_schema` for the columns. Header values which are not UTF-8 are null. For
+/// compressed inputs, offsets and lengths are relative to the decompressed stream.
+///
+/// If a record cannot be read, the rows read before it are returned first, then the error. No
+/// batches are returned after an error, since the position in the stream is then unknown.
+pub struct RecordBatchIter<R> {
+    inner: MetadataIter<R>,
+    batch_size: usize,
+    schema: SchemaRef,
+    error: Option<Error>,
+    done: bool,
+}
+
+impl<R: BufRead> RecordBatchIter<R> {
+    /// Create an iterator over batches of up to `batch_size` rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch_size` is zero.
+    pub fn new(reader: WarcReader<R>, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "batch size must not be zero");
+
+        RecordBatchIter {
+            inner: MetadataIter::new(reader, true),
+            batch_size,
+            schema: record_batch_schema(),
+            error: None,
+            done: false,
+        }
+    }
+
+    /// Return the schema of the batches returned.
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Write all of the remaining batches to a Parquet file, and return the number of rows written.
+    ///
+    /// Errors reading records are returned as `ParquetError::External`.
+    pub fn write_parquet<W: Write + Send>(self, writer: W) -> Result<u64, ParquetError> {
+        let mut writer = ArrowWriter::try_new(writer, self.schema(), None)?;
+        let mut rows = 0;
+        for batch in self {
+            let batch = batch.map_err(|e| ParquetError::External(Box::new(e)))?;
+            writer.write(&batch)?;
+            rows += batch.num_rows() as u64;
+        }
+        writer.close()?;
+
+        Ok(rows)
+    }
+}
+
+impl<R: BufRead> Iterator for RecordBatchIter<R> {
+    type Item = Result<RecordBatch, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+        if self.done {
+            return None;
+        }
+
+        let mut columns = Columns::new();
+        let mut rows = 0;
+        while rows < self.batch_size {
+            match self.inner.next() {
+                Some(Ok(metadata)) => columns.append(&metadata),
+                Some(Err(e)) => {
+                    self.done = true;
+                    if rows == 0 {
+                        return Some(Err(e));
+                    }
+                    self.error = Some(e);
+                    break;
+                }
+                None => break,
+            }
+            rows += 1;
+        }
+
+        if rows == 0 {
+            return None;
+        }
+        Some(Ok(columns.finish(self.schema.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RecordBatchIter;
+    use crate::WarcReader;
+
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{TimestampMillisecondType, UInt16Type, UInt64Type};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    const WARC: &[u8] = b"\
+WARC/1.0\r\n\
+WARC-Type: response\r\n\
+WARC-Record-ID: <urn:test:1>\r\n\
+WARC-Date: 2020-07-08T02:52:55Z\r\n\
+WARC-Target-URI: http://example.com/\r\n\
+Content-Type: application/http;msgtype=response\r\n\
+Content-Length: 73\r\n\
+\r\n\
+HTTP/1.1 301 Moved\r\n\
+Location: http://example.com/a\r\n\
+Content-Length: 0\r\n\
+\r\n\
+\r\n\
+\r\n\
+WARC/1.0\r\n\
+WARC-Type: resource\r\n\
+WARC-Record-ID: <urn:test:2>\r\n\
+WARC-Date: 2020-07-08T02:52:56Z\r\n\
+WARC-Segment-Number: 1\r\n\
+Content-Length: 5\r\n\
+\r\n\
+hello\r\n\
+\r\n\
+WARC/1.0\r\n\
+WARC-Type: resource\r\n\
+WARC-Record-ID: <urn:test:3>\r\n\
+WARC-Date: 2020-07-08T02:52:57Z\r\n\
+Content-Length: 0\r\n\
+\r\n\
+\r\n\
+\r\n";
+
+    #[test]
+    fn batches() {
+        let batches = RecordBatchIter::new(WarcReader::new(WARC), 2)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+
+        let batch = &batches[0];
+        let offset = batch["offset"].as_primitive::<UInt64Type>();
+        assert_eq!(offset.value(0), 0);
+        assert_eq!(
+            offset.value(1),
+            batch["length"].as_primitive::<UInt64Type>().value(0)
+        );
+        assert_eq!(
+            batch["content_length"]
+                .as_primitive::<UInt64Type>()
+                .value(1),
+            5
+        );
+        assert_eq!(
+            batch["warc_date"]
+                .as_primitive::<TimestampMillisecondType>()
+                .value(0),
+            1_594_176_775_000
+        );
+        assert_eq!(batch["warc_type"].as_string::<i32>().value(0), "response");
+        assert_eq!(
+            batch["warc_target_uri"].as_string::<i32>().value(0),
+            "http://example.com/"
+        );
+        assert!(batch["warc_target_uri"].is_null(1));
+        assert_eq!(
+            batch["warc_segment_number"]
+                .as_primitive::<UInt64Type>()
+                .value(1),
+            1
+        );
+        assert_eq!(
+            batch["http_status"].as_primitive::<UInt16Type>().value(0),
+            301
+        );
+        assert!(batch["http_status"].is_null(1));
+        assert_eq!(
+            batch["http_location"].as_string::<i32>().value(0),
+            "http://example.com/a"
+        );
+    }
+
+    #[test]
+    fn invalid_records() {
+        // a record without a date or type is still listed, and reading stops at a truncated one
+        let mut data = String::from_utf8(WARC.to_vec())
+            .unwrap()
+            .replace(
+                "WARC-Date: 2020-07-08T02:52:56Z\r\nWARC-Segment-Number: 1\r\n",
+                "",
+            )
+            .replacen("WARC-Type: resource\r\n", "", 1)
+            .into_bytes();
+        data.extend_from_slice(b"WARC/1.0\r\nContent-Length: 100\r\n\r\nshort");
+
+        let mut iter = RecordBatchIter::new(WarcReader::new(data.as_slice()), 10);
+        let batch = iter.next().unwrap().unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        assert!(batch["warc_date"].is_null(1));
+        assert!(batch["warc_type"].is_null(1));
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn write_parquet() {
+        let path = std::env::temp_dir().join(format!("{}.parquet", uuid::Uuid::new_v4()));
+        let file = std::fs::File::create(&path).unwrap();
+        let rows = RecordBatchIter::new(WarcReader::new(WARC), 2)
+            .write_parquet(file)
+            .unwrap();
+        assert_eq!(rows, 3);
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 3);
+        assert_eq!(
+            batches[0]["warc_record_id"].as_string::<i32>().value(2),
+            "<urn:test:3>"
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use std::convert::TryFrom;
+use std::io;
+use std::io::Write;
+
+use libflate::deflate::{EncodeOptions, Encoder as DeflateEncoder};
+use libflate::lz77::DefaultLz77EncoderBuilder;
+
+/// The library used to compress GZIP members.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GzipBackend {
+    /// Compress with `libflate`, in pure Rust. This is the default.
+    Libflate,
+    /// Compress with `flate2`, which uses zlib-ng when the `zlib-ng` feature is enabled.
+    #[cfg(feature = "flate2")]
+    Flate2,
+}
+
+/// Options for writing GZIP-compressed WARC files, with every record in its own GZIP member.
+///
+/// ```
+/// use warc::{WarcWriter, WarcWriterOptions};
+///
+/// let options = WarcWriterOptions::new().level(9).record_length_hint(true);
+/// let writer = WarcWriter::with_options(Vec::new(), options);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WarcWriterOptions {
+    level: u32,
+    backend: GzipBackend,
+    record_length_hint: bool,
+    extra_fields: Vec<([u8; 2], Vec<u8>)>,
+}
+
+impl Default for WarcWriterOptions {
+    fn default() -> Self {
+        WarcWriterOptions {
+            level: 6,
+            backend: GzipBackend::Libflate,
+            record_length_hint: false,
+            extra_fields: Vec::new(),
+        }
+    }
+}
+
+impl WarcWriterOptions {
+    /// Create the default options: level 6 compression with `libflate`, and no extra fields.
+    pub fn new() -> Self {
+        WarcWriterOptions::default()
+    }
+
+    /// Set the compression level, from 0 (no compression) to 9 (best compression).
+    ///
+    /// `libflate` has no levels of its own, so levels 1 to 5 shrink its search window instead,
+    /// trading ratio for speed. Levels 6 and above are equivalent with `libflate`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the level is greater than 9.
+    pub fn level(mut self, level: u32) -> Self {
+        assert!(level <= 9, "compression levels range from 0 to 9");
+        self.level = level;
+        self
+    }
+
+    /// Set the library used to compress.
+    pub fn backend(mut self, backend: GzipBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Set whether to add an `sl` extra field to every member.
+    ///
+    /// The field holds the length of the whole member, then the length of the uncompressed
+    /// record, each as a 32-bit little-endian integer. Readers can use it to skip a record without
+    /// decompressing it. Lengths which do not fit are written as zero.
+    pub fn record_length_hint(mut self, record_length_hint: bool) -> Self {
+        self.record_length_hint = record_length_hint;
+        self
+    }
+
+    /// Add an extra subfield, with the same content, to the header of every member.
+    pub fn extra_field<V: Into<Vec<u8>>>(mut self, id: [u8; 2], data: V) -> Self {
+        self.extra_fields.push((id, data.into()));
+        self
+    }
+
+    fn deflate(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self.backend {
+            GzipBackend::Libflate => {
+                let options = if self.level == 0 {
+                    EncodeOptions::with_lz77(DefaultLz77EncoderBuilder::new().build())
+                        .no_compression()
+                } else {
+                    let window_size = 512 << self.level.min(6);
+                    let lz77 = DefaultLz77EncoderBuilder::new()
+                        .window_size(window_size)
+                        .build();
+                    EncodeOptions::with_lz77(lz77)
+                };
+                let mut encoder = DeflateEncoder::with_options(Vec::new(), options);
+                encoder.write_all(data)?;
+                encoder.finish().into_result()
+            }
+            #[cfg(feature = "flate2")]
+            GzipBackend::Flate2 => {
+                let mut encoder = flate2::write::DeflateEncoder::new(
+                    Vec::new(),
+                    flate2::Compression::new(self.level),
+                );
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    /// Compress a serialized record into a single GZIP member.
+    pub(crate) fn compress_member(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let deflated = self.deflate(data)?;
+
+        let mut extra = Vec::new();
+        for (id, value) in self.extra_fields.iter() {
+            let len = u16::try_from(value.len())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "extra field too long"))?;
+            extra.extend_from_slice(id);
+            extra.extend_from_slice(&len.to_le_bytes());
+            extra.extend_from_slice(value);
+        }
+        let hint_offset = if self.record_length_hint {
+            extra.extend_from_slice(b"sl");
+            extra.extend_from_slice(&8u16.to_le_bytes());
+            extra.extend_from_slice(&[0; 8]);
+            Some(extra.len() - 8)
+        } else {
+            None
+        };
+        let xlen = u16::try_from(extra.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "extra fields too long"))?;
+
+        let flags = if extra.is_empty() { 0 } else { 4 };
+        let extra_flags = match self.level {
+            9 => 2,
+            1 => 4,
+            _ => 0,
+        };
+        let mut member = Vec::with_capacity(20 + extra.len() + deflated.len());
+        member.extend_from_slice(&[31, 139, 8, flags, 0, 0, 0, 0, extra_flags, 255]);
+        if !extra.is_empty() {
+            member.extend_from_slice(&xlen.to_le_bytes());
+            member.extend_from_slice(&extra);
+        }
+        let header_len = member.len();
+
+        member.extend_from_slice(&deflated);
+        member.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+        member.extend_from_slice(&(data.len() as u32).to_le_bytes());
+
+        if let Some(offset) = hint_offset {
+            let offset = header_len - extra.len() + offset;
+            let member_len = u32::try_from(member.len()).unwrap_or(0);
+            let data_len = u32::try_from(data.len()).unwrap_or(0);
+            member[offset..offset + 4].copy_from_slice(&member_len.to_le_bytes());
+            member[offset + 4..offset + 8].copy_from_slice(&data_len.to_le_bytes());
+        }
+
+        Ok(member)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GzipBackend, WarcWriterOptions};
+    use crate::{RecordBuilder, RecordType, WarcReader, WarcWriter};
+
+    use libflate::gzip::{Decoder as GzipReader, MultiDecoder};
+    use std::io::{BufReader, Cursor, Read};
+
+    type Subfields = Vec<(Vec<u8>, Vec<u8>)>;
+
+    fn decompress(member: &[u8]) -> (Vec<u8>, Option<Subfields>) {
+        let mut input = member;
+        let mut decoder = GzipReader::new(&mut input).unwrap();
+        let extra = decoder.header().extra_field().map(|extra| {
+            extra
+                .subfields
+                .iter()
+                .map(|field| (field.id.to_vec(), field.data.clone()))
+                .collect()
+        });
+        let mut data = Vec::new();
+        decoder.read_to_end(&mut data).unwrap();
+        assert!(input.is_empty());
+
+        (data, extra)
+    }
+
+    #[test]
+    fn levels() {
+        let data = b"a body which compresses well, well, well, well, well".repeat(20);
+
+        let mut sizes = Vec::new();
+        for level in [0, 1, 6, 9] {
+            let options = WarcWriterOptions::new().level(level);
+            let member = options.compress_member(&data).unwrap();
+            assert_eq!(decompress(&member), (data.clone(), None));
+            sizes.push(member.len());
+        }
+        assert!(sizes[0] > data.len());
+        assert!(sizes[1] < data.len());
+    }
+
+    #[test]
+    fn extra_fields() {
+        let data = b"record".to_vec();
+        let options = WarcWriterOptions::new()
+            .extra_field(*b"xy", "custom")
+            .record_length_hint(true);
+        let member = options.compress_member(&data).unwrap();
+
+        let mut hint = (member.len() as u32).to_le_bytes().to_vec();
+        hint.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        assert_eq!(
+            decompress(&member),
+            (
+                data,
+                Some(vec![
+                    (b"xy".to_vec(), b"custom".to_vec()),
+                    (b"sl".to_vec(), hint)
+                ])
+            )
+        );
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn flate2_backend() {
+        let data = b"a body which compresses well, well, well, well, well".repeat(20);
+        let options = WarcWriterOptions::new()
+            .backend(GzipBackend::Flate2)
+            .level(9)
+            .record_length_hint(true);
+        let member = options.compress_member(&data).unwrap();
+        assert_eq!(decompress(&member).0, data);
+    }
+
+    #[test]
+    fn member_per_record() {
+        let records = (0..3)
+            .map(|i| {
+                RecordBuilder::default()
+                    .warc_type(RecordType::Resource)
+                    .body(format!("record {}", i).into_bytes())
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let options = WarcWriterOptions::new()
+            .backend(GzipBackend::Libflate)
+            .record_length_hint(true);
+        let mut data = Vec::new();
+        let mut writer = WarcWriter::with_options(&mut data, options);
+        for record in records.iter() {
+            writer.write(record).unwrap();
+        }
+
+        let mut rest = &data[..];
+        for record in records.iter() {
+            let hinted = u32::from_le_bytes([rest[16], rest[17], rest[18], rest[19]]) as usize;
+            let (serialized, _) = decompress(&rest[..hinted]);
+            assert_eq!(
+                serialized.len(),
+                WarcWriter::new(Vec::new()).write(record).unwrap()
+            );
+            rest = &rest[hinted..];
+        }
+        assert!(rest.is_empty());
+
+        let reader = WarcReader::new(BufReader::new(
+            MultiDecoder::new(Cursor::new(data)).unwrap(),
+        ));
+        let read = reader
+            .iter_records()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(read.len(), records.len());
+        assert_eq!(read[2].body(), records[2].body());
+    }
+}
@@ -4,7 +4,10 @@ use std::fmt;
 use crate::header::WarcHeader;
 
 /// An error type returned by WARC header parsing.
+///
+/// New variants may be added in future releases, so matches on this type need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// An error occured identifing or parsing headers.
     ParseHeaders(nom::Err<(Vec<u8>, nom::error::ErrorKind)>),
@@ -15,11 +18,20 @@ pub enum Error {
     MalformedHeader(WarcHeader, String),
     /// The underlying read from the data source failed.
     ReadData(std::io::Error),
+    /// The underlying write to an output stream failed.
+    WriteData(std::io::Error),
     /// More data was read than expected by the header metadata. The record was well-formed, but
     /// invalid.
     ReadOverflow,
     /// The end of the record's body was found unexpectedly.
     UnexpectedEOB,
+    /// The stream ended before all segments of a segmented record were read. Contains the record
+    /// ID of the first segment, and the segment numbers known to be missing.
+    MissingSegments(String, Vec<u64>),
+    /// A line of a CDX or CDXJ index could not be parsed. Contains the reason.
+    ParseIndex(String),
+    /// A wayback timestamp is not 4 to 14 digits naming a valid date. Contains the timestamp.
+    ParseTimestamp(String),
 }
 
 impl fmt::Display for Error {
@@ -31,8 +43,14 @@ impl fmt::Display for Error {
                 write!(f, "Malformed header: {}: {}", h, r)
             }
             Error::ReadData(_) => write!(f, "Error reading data source."),
+            Error::WriteData(_) => write!(f, "Error writing output."),
             Error::ReadOverflow => write!(f, "Read further than expected."),
             Error::UnexpectedEOB => write!(f, "Unexpected end of body."),
+            Error::MissingSegments(ref id, ref missing) => {
+                write!(f, "Missing segments of record {}: {:?}", id, missing)
+            }
+            Error::ParseIndex(ref r) => write!(f, "Error parsing index: {}", r),
+            Error::ParseTimestamp(ref t) => write!(f, "Invalid timestamp: {}", t),
         }
     }
 }
@@ -42,6 +60,7 @@ impl error::Error for Error {
         match self {
             Error::ParseHeaders(ref e) => Some(e),
             Error::ReadData(ref e) => Some(e),
+            Error::WriteData(ref e) => Some(e),
             _ => None,
         }
     }
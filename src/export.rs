@@ -0,0 +1,433 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+use crate::{
+    Error, HeaderIter, MediaType, RawRecordHeader, RecordLocation, RecordType, WarcHeader,
+    WarcReader,
+};
+
+/// The most bytes of a record body read while looking for the end of an HTTP header block.
+const HTTP_HEAD_LIMIT: u64 = 64 * 1_024;
+
+/// A field of the JSON objects written by a `JsonLinesExporter`.
+///
+/// Fields which do not apply to a record are written as `null`, so that every line has the same
+/// keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExportField {
+    /// `offset`: the number of bytes before the record in the stream read.
+    Offset,
+    /// `length`: the length of the record, including its header block and the line endings after
+    /// its body.
+    Length,
+    /// `headers`: all WARC headers, keyed by their lower-case names.
+    Headers,
+    /// A single WARC header, keyed by its lower-case name.
+    Header(WarcHeader),
+    /// `http_status`: the status code of an HTTP response.
+    HttpStatus,
+    /// `http_headers`: the headers of an HTTP request or response, keyed by their lower-case
+    /// names. Repeated headers are joined with commas.
+    HttpHeaders,
+    /// `block_digest` and `payload_digest`: the values of the digest headers.
+    Digests,
+}
+
+impl ExportField {
+    /// Return the fields written when none are chosen.
+    pub fn defaults() -> Vec<ExportField> {
+        vec![
+            ExportField::Offset,
+            ExportField::Length,
+            ExportField::Headers,
+            ExportField::HttpStatus,
+            ExportField::HttpHeaders,
+            ExportField::Digests,
+        ]
+    }
+
+    fn needs_http(&self) -> bool {
+        matches!(self, ExportField::HttpStatus | ExportField::HttpHeaders)
+    }
+}
+
+/// The status code and headers of an HTTP message.
+pub(crate) struct HttpHead {
+    pub(crate) status: Option<u16>,
+    pub(crate) headers: BTreeMap<String, String>,
+}
+
+impl HttpHead {
+    /// Parse the header block at the start of an HTTP message, if it is complete.
+    fn parse(block: &[u8], is_response: bool) -> Option<HttpHead> {
+        let end = block.windows(4).position(|window| window == b"\r\n\r\n")?;
+        let head = String::from_utf8_lossy(&block[..end]);
+        let mut lines = head.split("\r\n");
+
+        let status = lines
+            .next()
+            .filter(|_| is_response)
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|status| status.parse().ok());
+
+        let mut headers: BTreeMap<String, String> = BTreeMap::new();
+        let mut last_name: Option<String> = None;
+        for line in lines {
+            if line.starts_with([' ', '\t']) {
+                // NB: obsolete line folding continues the previous header
+                if let Some(value) = last_name.as_ref().and_then(|name| headers.get_mut(name)) {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+                continue;
+            }
+            let (name, value) = match line.split_once(':') {
+                Some((name, value)) => (name.trim().to_ascii_lowercase(), value.trim()),
+                None => continue,
+            };
+            headers
+                .entry(name.clone())
+                .and_modify(|joined| {
+                    joined.push_str(", ");
+                    joined.push_str(value);
+                })
+                .or_insert_with(|| value.to_owned());
+            last_name = Some(name);
+        }
+
+        Some(HttpHead { status, headers })
+    }
+}
+
+/// The metadata of a record read by a `MetadataIter`.
+pub(crate) struct RecordMetadata {
+    pub(crate) headers: RawRecordHeader,
+    pub(crate) location: RecordLocation,
+    /// The head of the HTTP message in the body, if there is one and it was read.
+    pub(crate) http: Option<HttpHead>,
+}
+
+/// Return whether a record holds an HTTP response (`Some(true)`) or request (`Some(false)`).
+///
+/// Only the raw headers are read, so that records with other malformed headers are still
+/// exported. See `Record::is_http_response`.
+fn http_message(headers: &RawRecordHeader) -> Option<bool> {
+    let content_type = headers.as_ref().get(&WarcHeader::ContentType)?;
+    let content_type = MediaType::parse(std::str::from_utf8(content_type).ok()?).ok()?;
+    if !content_type.is("application", "http") {
+        return None;
+    }
+
+    match content_type.param("msgtype") {
+        Some(msgtype) if msgtype.eq_ignore_ascii_case("response") => Some(true),
+        Some(msgtype) if msgtype.eq_ignore_ascii_case("request") => Some(false),
+        Some(_) => None,
+        None => {
+            let record_type = headers.as_ref().get(&WarcHeader::WarcType)?;
+            match RecordType::from(String::from_utf8_lossy(record_type)) {
+                RecordType::Response => Some(true),
+                RecordType::Request => Some(false),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// An iterator over the headers and locations of the records read by a reader, and the heads of
+/// the HTTP messages they hold.
+pub(crate) struct MetadataIter<R> {
+    inner: HeaderIter<R>,
+    read_http: bool,
+}
+
+impl<R: BufRead> MetadataIter<R> {
+    /// Create an iterator which parses the heads of HTTP messages if `read_http` is set.
+    pub(crate) fn new(reader: WarcReader<R>, read_http: bool) -> MetadataIter<R> {
+        MetadataIter {
+            inner: reader.iter_headers(),
+            read_http,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for MetadataIter<R> {
+    type Item = Result<RecordMetadata, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let read_http = self.read_http;
+        let next = self.inner.next_with_prefix(HTTP_HEAD_LIMIT, |headers| {
+            read_http && http_message(headers).is_some()
+        })?;
+        let (headers, location, head) = match next {
+            Ok(next) => next,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let http = head.and_then(|head| HttpHead::parse(&head, http_message(&headers)?));
+        Some(Ok(RecordMetadata {
+            headers,
+            location,
+            http,
+        }))
+    }
+}
+
+/// Writes the metadata of records as JSON Lines, one object per record.
+///
+/// Each line holds the fields chosen with `fields`, in that order. Header values which are not
+/// UTF-8 are converted lossily. For compressed inputs, offsets and lengths are relative to the
+/// decompressed stream.
+///
+/// HTTP headers are parsed from the first 64 KiB of the body of records holding HTTP messages.
+/// The rest of each body is skipped as efficiently as the reader allows.
+pub struct JsonLinesExporter<W> {
+    writer: W,
+    fields: Vec<ExportField>,
+}
+
+impl<W: Write> JsonLinesExporter<W> {
+    /// Create an exporter which writes the default fields to a stream.
+    pub fn new(writer: W) -> Self {
+        JsonLinesExporter {
+            writer,
+            fields: ExportField::defaults(),
+        }
+    }
+
+    /// Set the fields written for each record.
+    pub fn fields(mut self, fields: Vec<ExportField>) -> Self {
+        self.fields = fields;
+
+        self
+    }
+
+    /// Write a line for each of the records read, and return the number of lines written.
+    ///
+    /// Stops at the first record which cannot be read. Errors writing the output are returned as
+    /// `Error::WriteData`.
+    pub fn export<R: BufRead>(&mut self, reader: WarcReader<R>) -> Result<u64, Error> {
+        let needs_http = self.fields.iter().any(ExportField::needs_http);
+        let mut count = 0;
+        for metadata in MetadataIter::new(reader, needs_http) {
+            let metadata = metadata?;
+            let line = self.format_line(
+                &metadata.location,
+                &metadata.headers,
+                metadata.http.as_ref(),
+            );
+            self.writer
+                .write_all(line.as_bytes())
+                .map_err(Error::WriteData)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Format the JSON object of a record, with its line ending.
+    fn format_line(
+        &self,
+        location: &RecordLocation,
+        headers: &RawRecordHeader,
+        http: Option<&HttpHead>,
+    ) -> String {
+        let values = headers
+            .as_ref()
+            .iter()
+            .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value)))
+            .collect::<BTreeMap<_, _>>();
+        let header = |name: &WarcHeader| values.get(&name.to_string()).map(|v| v.as_ref());
+
+        let mut line = String::from("{");
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                line.push(',');
+            }
+            match field {
+                ExportField::Offset => {
+                    let _ = write!(line, "\"offset\":{}", location.offset);
+                }
+                ExportField::Length => {
+                    let _ = write!(line, "\"length\":{}", location.length);
+                }
+                ExportField::Headers => {
+                    line.push_str("\"headers\":");
+                    push_object(
+                        &mut line,
+                        values.iter().map(|(k, v)| (k.as_str(), v.as_ref())),
+                    );
+                }
+                ExportField::Header(name) => {
+                    push_string(&mut line, &name.to_string());
+                    line.push(':');
+                    push_optional(&mut line, header(name));
+                }
+                ExportField::HttpStatus => {
+                    line.push_str("\"http_status\":");
+                    match http.and_then(|http| http.status) {
+                        Some(status) => {
+                            let _ = write!(line, "{}", status);
+                        }
+                        None => line.push_str("null"),
+                    }
+                }
+                ExportField::HttpHeaders => {
+                    line.push_str("\"http_headers\":");
+                    match http {
+                        Some(http) => push_object(
+                            &mut line,
+                            http.headers.iter().map(|(k, v)| (k.as_str(), v.as_str())),
+                        ),
+                        None => line.push_str("null"),
+                    }
+                }
+                ExportField::Digests => {
+                    line.push_str("\"block_digest\":");
+                    push_optional(&mut line, header(&WarcHeader::BlockDigest));
+                    line.push_str(",\"payload_digest\":");
+                    push_optional(&mut line, header(&WarcHeader::PayloadDigest));
+                }
+            }
+        }
+        line.push_str("}\n");
+
+        line
+    }
+
+    /// Flush the output, and return the stream written to.
+    pub fn into_inner(mut self) -> Result<W, Error> {
+        self.writer.flush().map_err(Error::WriteData)?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Append a JSON string literal.
+fn push_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Append a JSON string literal, or `null`.
+fn push_optional(out: &mut String, value: Option<&str>) {
+    match value {
+        Some(value) => push_string(out, value),
+        None => out.push_str("null"),
+    }
+}
+
+/// Append a JSON object with string values.
+fn push_object<'a, I: Iterator<Item = (&'a str, &'a str)>>(out: &mut String, entries: I) {
+    out.push('{');
+    for (i, (name, value)) in entries.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_string(out, name);
+        out.push(':');
+        push_string(out, value);
+    }
+    out.push('}');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExportField, JsonLinesExporter};
+    use crate::{Error, WarcHeader, WarcReader};
+
+    use std::io;
+
+    const WARC: &[u8] = b"\
+WARC/1.0\r\n\
+WARC-Type: response\r\n\
+WARC-Record-ID: <urn:test:1>\r\n\
+WARC-Date: 2020-07-08T02:52:55Z\r\n\
+WARC-Target-URI: http://example.com/\r\n\
+WARC-Payload-Digest: sha1:AAAA\r\n\
+Content-Type: application/http;msgtype=response\r\n\
+Content-Length: 80\r\n\
+\r\n\
+HTTP/1.1 200 OK\r\n\
+Content-Type: text/html\r\n\
+Set-Cookie: a=1\r\n\
+Set-Cookie: b=2\r\n\
+\r\n\
+hi\r\n\
+\r\n\
+WARC/1.0\r\n\
+WARC-Type: resource\r\n\
+WARC-Record-ID: <urn:test:\"2\">\r\n\
+WARC-Date: 2020-07-08T02:52:56Z\r\n\
+Content-Length: 5\r\n\
+\r\n\
+hello\r\n\
+\r\n";
+
+    #[test]
+    fn export_default_fields() {
+        let mut exporter = JsonLinesExporter::new(Vec::new());
+        let count = exporter.export(WarcReader::new(WARC)).unwrap();
+        assert_eq!(count, 2);
+
+        let output = String::from_utf8(exporter.into_inner().unwrap()).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "{\"offset\":0,\"length\":319,\"headers\":{\"content-length\":\"80\",\
+             \"content-type\":\"application/http;msgtype=response\",\
+             \"warc-date\":\"2020-07-08T02:52:55Z\",\"warc-payload-digest\":\"sha1:AAAA\",\
+             \"warc-record-id\":\"<urn:test:1>\",\"warc-target-uri\":\"http://example.com/\",\
+             \"warc-type\":\"response\"},\"http_status\":200,\
+             \"http_headers\":{\"content-type\":\"text/html\",\"set-cookie\":\"a=1, b=2\"},\
+             \"block_digest\":null,\"payload_digest\":\"sha1:AAAA\"}"
+        );
+        assert!(lines[1].starts_with("{\"offset\":319,"));
+        assert!(lines[1].contains("\"warc-record-id\":\"<urn:test:\\\"2\\\">\""));
+        assert!(lines[1].contains("\"http_status\":null,\"http_headers\":null"));
+    }
+
+    #[test]
+    fn export_selected_fields() {
+        let mut exporter = JsonLinesExporter::new(Vec::new()).fields(vec![
+            ExportField::Header(WarcHeader::TargetURI),
+            ExportField::HttpStatus,
+        ]);
+        exporter.export(WarcReader::new(WARC)).unwrap();
+
+        assert_eq!(
+            String::from_utf8(exporter.into_inner().unwrap()).unwrap(),
+            "{\"warc-target-uri\":\"http://example.com/\",\"http_status\":200}\n\
+             {\"warc-target-uri\":null,\"http_status\":null}\n"
+        );
+    }
+
+    #[test]
+    fn export_write_error() {
+        struct Failing;
+        impl io::Write for Failing {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let result = JsonLinesExporter::new(Failing).export(WarcReader::new(WARC));
+        assert!(matches!(result, Err(Error::WriteData(_))));
+    }
+}
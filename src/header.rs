@@ -39,9 +39,10 @@ impl From<WarcHeader> for String {
     }
 }
 
-impl Display for WarcHeader {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let stringified = match self {
+impl WarcHeader {
+    /// Return the lowercase name of this header, as written by `Display`.
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
             WarcHeader::ContentLength => "content-length",
             WarcHeader::ContentType => "content-type",
             WarcHeader::BlockDigest => "warc-block-digest",
@@ -62,8 +63,13 @@ impl Display for WarcHeader {
             WarcHeader::WarcType => "warc-type",
             WarcHeader::WarcInfoID => "warc-warcinfo-id",
             WarcHeader::Unknown(ref string) => string,
-        };
-        write!(f, "{}", stringified)
+        }
+    }
+}
+
+impl Display for WarcHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
     }
 }
 
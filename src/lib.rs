@@ -9,17 +9,74 @@ pub use warc_reader::*;
 mod warc_writer;
 pub use warc_writer::*;
 
+#[cfg(feature = "gzip")]
+mod compression;
+#[cfg(feature = "gzip")]
+pub use compression::{GzipBackend, WarcWriterOptions};
+
+mod reassemble;
+pub use reassemble::{ReassembledRecord, SegmentedRecordIter};
+
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "rayon")]
+pub use parallel::{ParallelGzipReader, ParallelGzipWriter, ParallelRecordIter};
+
+mod cdx;
+pub use cdx::{CdxIndex, CdxRecord};
+
+mod cdx_search;
+pub use cdx_search::SortedCdxFile;
+#[cfg(feature = "gzip")]
+pub use cdx_search::ZipNumIndex;
+
+mod collection;
+pub use collection::Collection;
+
+mod export;
+pub use export::{ExportField, JsonLinesExporter};
+
+#[cfg(feature = "arrow")]
+mod columnar;
+#[cfg(feature = "arrow")]
+pub use columnar::{record_batch_schema, RecordBatchIter};
+
+pub mod surt;
+
+pub mod timestamp;
+
+pub mod validate;
+
 mod header;
 pub use header::WarcHeader;
 
 /// Core functions for parsing. Not recommended for direct use.
 pub mod parser;
 
+mod media_type;
+pub use media_type::MediaType;
+
 mod record;
+#[cfg(feature = "with_serde")]
+pub use record::{BodyEncoding, EncodedRecord};
 pub use record::{BufferedBody, EmptyBody, RawRecordHeader, Record, RecordBuilder, StreamingBody};
 
+mod record_id;
+pub use record_id::{RecordId, RecordIdGenerator, UlidGenerator, UuidV4Generator, UuidV5Generator};
+
 mod record_type;
 pub use record_type::RecordType;
 
 mod truncated_type;
 pub use truncated_type::TruncatedType;
+
+mod warc_slice;
+#[cfg(feature = "mmap")]
+pub use warc_slice::MappedWarc;
+pub use warc_slice::{RecordRef, SliceRecordIter, WarcSlice};
+
+mod warc_fields;
+pub use warc_fields::WarcFields;
+
+mod warcinfo;
+pub use warcinfo::WarcInfo;
@@ -0,0 +1,216 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::header::WarcHeader;
+use crate::Error;
+
+/// A media type, as used by the `Content-Type` and `WARC-Identified-Payload-Type` headers.
+///
+/// The type, subtype and parameter names are compared case-insensitively, and are kept in lower
+/// case. Parameter values are kept as they were written, without quotes.
+///
+/// Use the `Display` trait to generate the formatted representation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MediaType {
+    type_: String,
+    subtype: String,
+    params: Vec<(String, String)>,
+}
+
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b))
+}
+
+impl MediaType {
+    /// Create a media type without parameters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the type or subtype is not a valid token.
+    pub fn new(type_: &str, subtype: &str) -> Self {
+        assert!(
+            is_token(type_) && is_token(subtype),
+            "not a valid media type: {}/{}",
+            type_,
+            subtype
+        );
+
+        MediaType {
+            type_: type_.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Parse a media type, with optional parameters.
+    pub fn parse(media_type: &str) -> Result<Self, Error> {
+        MediaType::parse_header(WarcHeader::ContentType, media_type)
+    }
+
+    /// Parse the value of a header holding a media type.
+    pub(crate) fn parse_header(header: WarcHeader, media_type: &str) -> Result<Self, Error> {
+        let malformed = || Error::MalformedHeader(header.clone(), "not a media type".to_string());
+
+        let (essence, mut rest) = match media_type.find(';') {
+            Some(end) => (&media_type[..end], &media_type[end + 1..]),
+            None => (media_type, ""),
+        };
+        let (type_, subtype) = essence.trim().split_once('/').ok_or_else(malformed)?;
+        if !is_token(type_) || !is_token(subtype) {
+            return Err(malformed());
+        }
+        let mut parsed = MediaType::new(type_, subtype);
+
+        loop {
+            rest = rest.trim_start_matches(|c: char| c == ';' || c.is_ascii_whitespace());
+            if rest.is_empty() {
+                break;
+            }
+            let (name, value) = rest.split_once('=').ok_or_else(malformed)?;
+            let name = name.trim();
+            if !is_token(name) {
+                return Err(malformed());
+            }
+
+            let (value, remainder) = if let Some(quoted) = value.strip_prefix('"') {
+                let mut unquoted = String::new();
+                let mut chars = quoted.char_indices();
+                let end = loop {
+                    match chars.next() {
+                        Some((_, '\\')) => unquoted.extend(chars.next().map(|(_, c)| c)),
+                        Some((i, '"')) => break i,
+                        Some((_, c)) => unquoted.push(c),
+                        None => return Err(malformed()),
+                    }
+                };
+                let remainder = &quoted[end + 1..];
+                if !remainder.trim_start().is_empty() && !remainder.trim_start().starts_with(';') {
+                    return Err(malformed());
+                }
+                (unquoted, remainder)
+            } else {
+                let end = value.find(';').unwrap_or(value.len());
+                let token = value[..end].trim();
+                if !is_token(token) {
+                    return Err(malformed());
+                }
+                (token.to_owned(), &value[end..])
+            };
+
+            parsed.params.push((name.to_ascii_lowercase(), value));
+            rest = remainder;
+        }
+
+        Ok(parsed)
+    }
+
+    /// Return the top-level type, such as `text`.
+    pub fn type_(&self) -> &str {
+        &self.type_
+    }
+
+    /// Return the subtype, such as `html`.
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// Return the type and subtype without parameters, such as `text/html`.
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.type_, self.subtype)
+    }
+
+    /// Return the value of a parameter, or `None`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Return all parameters, in the order they were written.
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    /// Add a parameter, replacing any with the same name.
+    pub fn with_param(mut self, name: &str, value: &str) -> Self {
+        let name = name.to_ascii_lowercase();
+        self.params.retain(|(key, _)| *key != name);
+        self.params.push((name, value.to_owned()));
+
+        self
+    }
+
+    /// Return `true` if this media type has the given type and subtype.
+    pub fn is(&self, type_: &str, subtype: &str) -> bool {
+        self.type_.eq_ignore_ascii_case(type_) && self.subtype.eq_ignore_ascii_case(subtype)
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.type_, self.subtype)?;
+        for (name, value) in self.params.iter() {
+            if is_token(value) {
+                write!(f, ";{}={}", name, value)?;
+            } else {
+                let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+                write!(f, ";{}=\"{}\"", name, escaped)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for MediaType {
+    type Err = Error;
+
+    fn from_str(media_type: &str) -> Result<Self, Error> {
+        MediaType::parse(media_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MediaType;
+
+    #[test]
+    fn parse_and_format() {
+        let parsed = MediaType::parse("Application/HTTP; msgtype=response").unwrap();
+        assert!(parsed.is("application", "http"));
+        assert_eq!(parsed.essence(), "application/http");
+        assert_eq!(parsed.param("MsgType"), Some("response"));
+        assert_eq!(parsed.to_string(), "application/http;msgtype=response");
+
+        let parsed =
+            MediaType::parse("text/plain;charset=\"utf-8\"; name=\"a \\\"b\\\";c\"").unwrap();
+        assert_eq!(parsed.param("charset"), Some("utf-8"));
+        assert_eq!(parsed.param("name"), Some("a \"b\";c"));
+        assert_eq!(
+            parsed.to_string(),
+            "text/plain;charset=utf-8;name=\"a \\\"b\\\";c\""
+        );
+        assert_eq!(parsed.to_string().parse::<MediaType>().unwrap(), parsed);
+
+        let built = MediaType::new("text", "dns").with_param("a", "1");
+        assert_eq!(
+            built.with_param("A", "2").params(),
+            &[("a".into(), "2".into())]
+        );
+
+        for bad in [
+            "",
+            "text",
+            "text/",
+            "text/plain;charset",
+            "text/plain;charset=\"utf-8",
+            "text/plain;charset=\"utf-8\"x",
+            "te xt/plain",
+        ] {
+            assert!(MediaType::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,687 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::io::{Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use libflate::gzip::Decoder as GzipDecoder;
+use rayon::prelude::*;
+
+use crate::warc_writer::write_segmented;
+use crate::{
+    BufferedBody, Error, RawRecordHeader, Record, WarcSlice, WarcWriter, WarcWriterOptions,
+};
+
+const MB: usize = 1_048_576;
+
+type Member = Vec<Result<Record<BufferedBody>, Error>>;
+
+/// A reader which decompresses and parses the members of a GZIP-compressed WARC file in parallel.
+///
+/// Each GZIP member must hold whole records, as written by per-record compression. Member
+/// boundaries are read from the `sl` extra field when members have one, as written with
+/// `WarcWriterOptions::record_length_hint`. Otherwise they are found by scanning for plausible GZIP
+/// headers. Every candidate is decompressed and parsed on the rayon thread pool, and candidates
+/// which do not start where the previous member ended are discarded, so compressed bytes which
+/// happen to look like a header are harmless. Scanned candidates are only decompressed whole once
+/// their first bytes decompress to the start of a record.
+///
+/// The data is processed in batches of compressed bytes, and a batch ends early once its
+/// decompressed records reach a limit, which bounds the memory held by decompressed records. The
+/// data is usually a memory-mapped file, or a file read into memory.
+pub struct ParallelGzipReader<'a> {
+    data: &'a [u8],
+    batch_size: usize,
+    decompressed_limit: usize,
+}
+
+impl<'a> ParallelGzipReader<'a> {
+    /// Create a new reader over GZIP-compressed data.
+    pub fn new(data: &'a [u8]) -> Self {
+        ParallelGzipReader {
+            data,
+            batch_size: 64 * MB,
+            decompressed_limit: 256 * MB,
+        }
+    }
+
+    /// Set the number of compressed bytes scanned for members in each batch.
+    ///
+    /// A member starting in a batch is always read whole, even when it ends past the batch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the batch size is zero.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        assert_ne!(batch_size, 0, "batches must have a non-zero size");
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Set the number of decompressed bytes after which a batch ends.
+    ///
+    /// A batch always holds at least one member, however large it is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the limit is zero.
+    pub fn decompressed_limit(mut self, decompressed_limit: usize) -> Self {
+        assert_ne!(decompressed_limit, 0, "the limit must be non-zero");
+        self.decompressed_limit = decompressed_limit;
+        self
+    }
+
+    /// Create an iterator over all records, in the order they appear in the data.
+    ///
+    /// Reading stops after an error decompressing a member. An error parsing a record skips the
+    /// rest of its member.
+    pub fn records(self) -> ParallelRecordIter<'a> {
+        ParallelRecordIter {
+            batches: Batches {
+                data: self.data,
+                batch_size: self.batch_size,
+                decompressed_limit: self.decompressed_limit,
+                offset: 0,
+            },
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Call a closure on every record, on the rayon thread pool and in no particular order.
+    ///
+    /// Processing stops after the first error returned by the closure, or encountered reading the
+    /// data, which is then returned.
+    pub fn par_for_each<F>(self, f: F) -> Result<(), Error>
+    where
+        F: Fn(Record<BufferedBody>) -> Result<(), Error> + Sync + Send,
+    {
+        let batches = Batches {
+            data: self.data,
+            batch_size: self.batch_size,
+            decompressed_limit: self.decompressed_limit,
+            offset: 0,
+        };
+        for batch in batches {
+            batch
+                .into_par_iter()
+                .flatten()
+                .try_for_each(|record| record.and_then(&f))?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Batches<'a> {
+    data: &'a [u8],
+    batch_size: usize,
+    decompressed_limit: usize,
+    offset: usize,
+}
+
+impl<'a> Iterator for Batches<'a> {
+    type Item = Vec<Member>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.data.len() {
+            return None;
+        }
+
+        let data = self.data;
+        let end = data.len().min(self.offset + self.batch_size);
+        let max_mtime = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(u64::MAX, |now| now.as_secs() + 24 * 60 * 60);
+
+        // follow the member lengths from `sl` fields, and scan for headers from where they stop
+        let mut starts = Vec::new();
+        let mut start = self.offset;
+        while start < end {
+            match member_header(&data[start..], max_mtime) {
+                Some(Some(length)) => {
+                    starts.push(start);
+                    start += length;
+                }
+                _ => break,
+            }
+        }
+        // NB: the first scanned offset must be a member anyway, so it is decompressed unprobed to
+        // report why it is not
+        let first = start;
+        let scanned: Vec<usize> = (start..end)
+            .into_par_iter()
+            .filter(|&start| {
+                member_header(&data[start..], max_mtime).is_some()
+                    && (start == first || starts_with_record(&data[start..]))
+            })
+            .collect();
+        starts.extend(scanned);
+
+        // decompress a few candidates at a time, so a batch holding enough records stops early
+        let window = rayon::current_num_threads() * 2;
+        let mut candidates = starts.into_iter().peekable();
+        let mut members = Vec::new();
+        let mut held = 0;
+        while self.offset < end && (members.is_empty() || held < self.decompressed_limit) {
+            while candidates.peek().is_some_and(|&start| start < self.offset) {
+                candidates.next();
+            }
+            if candidates.peek() != Some(&self.offset) {
+                members.push(vec![Err(Error::ReadData(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("no GZIP member at offset {}", self.offset),
+                )))]);
+                self.offset = data.len();
+                break;
+            }
+
+            let mut decoded: HashMap<usize, Result<(usize, usize, Member), Error>> = candidates
+                .by_ref()
+                .take(window)
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map(|start| (start, decode_member(&data[start..])))
+                .collect();
+            while self.offset < end && (members.is_empty() || held < self.decompressed_limit) {
+                match decoded.remove(&self.offset) {
+                    Some(Ok((length, decompressed_len, records))) => {
+                        self.offset += length;
+                        held += decompressed_len;
+                        members.push(records);
+                    }
+                    Some(Err(e)) => {
+                        members.push(vec![Err(e)]);
+                        self.offset = data.len();
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        Some(members)
+    }
+}
+
+/// Check whether the data starts with a plausible GZIP member header.
+///
+/// Returns the length of the member given by its `sl` extra field, if it has one.
+fn member_header(data: &[u8], max_mtime: u64) -> Option<Option<usize>> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    // a header, an empty deflate stream, and the trailer
+    if data.len() < 20 {
+        return None;
+    }
+    // ID1, ID2, CM (deflate), and FLG with its reserved bits unset
+    let flags = data[3];
+    if data[..3] != [0x1f, 0x8b, 0x08] || flags & 0xe0 != 0 {
+        return None;
+    }
+    let mtime = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+    if u64::from(mtime) > max_mtime {
+        return None;
+    }
+    // XFL is only set for the fastest and the best compression, and OS is a known value
+    if ![0, 2, 4].contains(&data[8]) || (data[9] > 13 && data[9] != 255) {
+        return None;
+    }
+
+    let mut pos = 10;
+    let mut length = None;
+    if flags & FEXTRA != 0 {
+        let xlen = usize::from(u16::from_le_bytes([data[10], data[11]]));
+        let extra = data.get(12..12 + xlen)?;
+        let mut fields = extra;
+        while !fields.is_empty() {
+            let len = usize::from(u16::from_le_bytes([*fields.get(2)?, *fields.get(3)?]));
+            let field = fields.get(4..4 + len)?;
+            if fields[1] == 0 {
+                return None;
+            }
+            if fields[..2] == *b"sl" && len == 8 {
+                let member_len = u32::from_le_bytes([field[0], field[1], field[2], field[3]]);
+                // zero is written when the length does not fit
+                if member_len != 0 {
+                    let member_len = member_len as usize;
+                    if member_len < 20 || member_len > data.len() {
+                        return None;
+                    }
+                    length = Some(member_len);
+                }
+            }
+            fields = &fields[4 + len..];
+        }
+        pos += 2 + xlen;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            pos += data.get(pos..)?.iter().position(|&b| b == 0)? + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        let crc = data.get(pos..pos + 2)?;
+        if crc32fast::hash(&data[..pos]).to_le_bytes()[..2] != *crc {
+            return None;
+        }
+        pos += 2;
+    }
+    if pos + 10 > data.len() {
+        return None;
+    }
+
+    Some(length)
+}
+
+/// Check whether the GZIP member the data starts with decompresses to the start of a record.
+///
+/// Only the first deflate block is decompressed.
+fn starts_with_record(data: &[u8]) -> bool {
+    let mut prefix = [0; 5];
+    GzipDecoder::new(data)
+        .and_then(|mut decoder| decoder.read_exact(&mut prefix))
+        .is_ok_and(|()| prefix == *b"WARC/")
+}
+
+/// Decompress and parse the GZIP member the data starts with.
+///
+/// Returns the compressed and decompressed lengths of the member, and its records.
+fn decode_member(data: &[u8]) -> Result<(usize, usize, Member), Error> {
+    let mut input = data;
+    let mut decompressed = Vec::new();
+    GzipDecoder::new(&mut input)
+        .and_then(|mut decoder| decoder.read_to_end(&mut decompressed))
+        .map_err(Error::ReadData)?;
+
+    let records = WarcSlice::new(&decompressed)
+        .iter()
+        .map(|record| record.and_then(|record| record.to_record()))
+        .collect();
+
+    Ok((data.len() - input.len(), decompressed.len(), records))
+}
+
+/// An iterator over the records read in parallel by a `ParallelGzipReader`, in file order.
+///
+/// Each batch of members is decompressed and parsed in parallel when the records of the previous
+/// batch are exhausted.
+pub struct ParallelRecordIter<'a> {
+    batches: Batches<'a>,
+    queue: VecDeque<Result<Record<BufferedBody>, Error>>,
+}
+
+impl<'a> Iterator for ParallelRecordIter<'a> {
+    type Item = Result<Record<BufferedBody>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.queue.pop_front() {
+                return Some(record);
+            }
+            let batch = self.batches.next()?;
+            self.queue.extend(batch.into_iter().flatten());
+        }
+    }
+}
+
+/// A writer which compresses every record into its own GZIP member in parallel.
+///
+/// Records are serialized as they are written, and compressed on the rayon thread pool once a
+/// batch of them is pending. Members are always written to the output stream in the order their
+/// records were written.
+///
+/// # Finishing the Output Stream
+///
+/// Records still pending are compressed and written when the writer is dropped, but any error
+/// doing so is ignored. Call `finish` to handle those errors, and to recover the output stream.
+pub struct ParallelGzipWriter<W: Write> {
+    // NB: only `None` once `finish` took the stream
+    writer: Option<W>,
+    serializer: WarcWriter<Vec<u8>>,
+    pending: Vec<Vec<u8>>,
+    pending_len: usize,
+    batch_size: usize,
+    max_body_len: Option<usize>,
+    options: WarcWriterOptions,
+}
+
+impl<W: Write> ParallelGzipWriter<W> {
+    /// Create a new writer, compressing with the default options.
+    pub fn new(w: W) -> Self {
+        ParallelGzipWriter::with_options(w, WarcWriterOptions::default())
+    }
+
+    /// Create a new writer, compressing with the given options.
+    pub fn with_options(w: W, options: WarcWriterOptions) -> Self {
+        ParallelGzipWriter {
+            writer: Some(w),
+            serializer: WarcWriter::new(Vec::new()),
+            pending: Vec::new(),
+            pending_len: 0,
+            batch_size: 16 * MB,
+            max_body_len: None,
+            options,
+        }
+    }
+
+    /// Set the number of uncompressed bytes to collect before compressing a batch of records.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the batch size is zero.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        assert_ne!(batch_size, 0, "batches must have a non-zero size");
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Set the maximum body length of records written by `write`.
+    ///
+    /// See `WarcWriter::set_max_body_length`. Every segment is compressed into its own member.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the maximum body length is zero.
+    pub fn set_max_body_length(&mut self, max_body_len: Option<usize>) {
+        assert_ne!(
+            max_body_len,
+            Some(0),
+            "segments must have a non-zero length"
+        );
+        self.max_body_len = max_body_len;
+    }
+
+    /// Set whether records written should reference the last `warcinfo` record written.
+    ///
+    /// See `WarcWriter::set_link_warcinfo`.
+    pub fn set_link_warcinfo(&mut self, link_warcinfo: bool) {
+        self.serializer.set_link_warcinfo(link_warcinfo);
+    }
+
+    /// Return the record ID of the last `warcinfo` record written, if any.
+    pub fn warcinfo_id(&self) -> Option<&str> {
+        self.serializer.warcinfo_id()
+    }
+
+    /// Write a single record.
+    ///
+    /// The number of uncompressed bytes written is returned upon success.
+    pub fn write(&mut self, record: &Record<BufferedBody>) -> io::Result<usize> {
+        let max_body_len = self.max_body_len;
+        write_segmented(record, max_body_len, |headers, body| {
+            self.write_raw(headers, &body)
+        })
+    }
+
+    /// Write a single raw record.
+    ///
+    /// The number of uncompressed bytes written is returned upon success.
+    pub fn write_raw<B>(&mut self, headers: RawRecordHeader, body: &B) -> io::Result<usize>
+    where
+        B: AsRef<[u8]>,
+    {
+        let bytes_written = self.serializer.write_raw(headers, body)?;
+        let serialized = std::mem::take(self.serializer.get_mut());
+
+        self.pending_len += serialized.len();
+        self.pending.push(serialized);
+        if self.pending_len >= self.batch_size {
+            self.compress_pending()?;
+        }
+
+        Ok(bytes_written)
+    }
+
+    /// Compress all pending records, and write them to the output stream.
+    fn compress_pending(&mut self) -> io::Result<()> {
+        let options = &self.options;
+        let members = std::mem::take(&mut self.pending)
+            .into_par_iter()
+            .map(|serialized| options.compress_member(&serialized))
+            .collect::<io::Result<Vec<_>>>()?;
+        self.pending_len = 0;
+
+        if let Some(writer) = self.writer.as_mut() {
+            for member in members {
+                writer.write_all(&member)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compress all pending records, flush the output stream, and return it.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.compress_pending()?;
+        let mut writer = self.writer.take().unwrap();
+        writer.flush()?;
+
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Drop for ParallelGzipWriter<W> {
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            let _ = self.compress_pending();
+            let _ = self.writer.as_mut().map(Write::flush);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        member_header, starts_with_record, Batches, ParallelGzipReader, ParallelGzipWriter,
+    };
+    use crate::{
+        Error, RecordBuilder, RecordType, WarcHeader, WarcInfo, WarcReader, WarcWriter,
+        WarcWriterOptions,
+    };
+
+    use libflate::gzip::{EncodeOptions, Encoder as GzipWriter, MultiDecoder as GzipReader};
+    use std::io::{BufReader, Cursor, Write};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn per_record_gzip(bodies: &[&[u8]]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for body in bodies {
+            let record = RecordBuilder::default()
+                .warc_type(RecordType::Resource)
+                .body(body.to_vec())
+                .build()
+                .unwrap();
+            let mut raw = Vec::new();
+            WarcWriter::new(&mut raw).write(&record).unwrap();
+
+            // stored blocks keep the body verbatim, so fake GZIP headers are visible
+            let options = EncodeOptions::new().no_compression();
+            let mut encoder = GzipWriter::with_options(data, options).unwrap();
+            encoder.write_all(&raw).unwrap();
+            data = encoder.finish().into_result().unwrap();
+        }
+
+        data
+    }
+
+    #[test]
+    fn records_in_order() {
+        let fake_header: &[u8] = b"\x1f\x8b\x08\x00\x00\x00\x00\x00\x00\xff";
+        let bodies: Vec<&[u8]> = vec![b"first", fake_header, b"third", b"fourth"];
+        let data = per_record_gzip(&bodies);
+
+        for batch_size in [1, 100, 1 << 20] {
+            let records = ParallelGzipReader::new(&data)
+                .batch_size(batch_size)
+                .records()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(records.iter().map(|r| r.body()).collect::<Vec<_>>(), bodies);
+        }
+    }
+
+    #[test]
+    fn member_headers() {
+        let data = per_record_gzip(&[b"first"]);
+        assert_eq!(member_header(&data, u64::MAX), Some(None));
+
+        let hinted = WarcWriterOptions::new()
+            .record_length_hint(true)
+            .compress_member(b"record")
+            .unwrap();
+        assert_eq!(member_header(&hinted, u64::MAX), Some(Some(hinted.len())));
+        assert_eq!(member_header(&hinted[..hinted.len() - 1], u64::MAX), None);
+
+        let mut implausible = data.clone();
+        implausible[8] = 7;
+        assert_eq!(member_header(&implausible, u64::MAX), None);
+
+        let mut implausible = data.clone();
+        implausible[9] = 100;
+        assert_eq!(member_header(&implausible, u64::MAX), None);
+
+        let mut implausible = data;
+        implausible[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(member_header(&implausible, 1_600_000_000), None);
+    }
+
+    #[test]
+    fn record_probes() {
+        let data = per_record_gzip(&[b"first"]);
+        assert!(starts_with_record(&data));
+
+        let not_a_record = WarcWriterOptions::new()
+            .compress_member(b"\x1f\x8b\x08 is not a record")
+            .unwrap();
+        assert!(!starts_with_record(&not_a_record));
+        assert!(!starts_with_record(&data[..12]));
+    }
+
+    #[test]
+    fn decompressed_limit() {
+        let bodies: Vec<&[u8]> = vec![b"first", b"second", b"third"];
+        let data = per_record_gzip(&bodies);
+
+        let batches = Batches {
+            data: &data,
+            batch_size: data.len(),
+            decompressed_limit: 1,
+            offset: 0,
+        };
+        let lengths = batches.map(|batch| batch.len()).collect::<Vec<_>>();
+        assert_eq!(lengths, vec![1, 1, 1]);
+
+        let records = ParallelGzipReader::new(&data)
+            .decompressed_limit(1)
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records.iter().map(|r| r.body()).collect::<Vec<_>>(), bodies);
+    }
+
+    #[test]
+    fn par_for_each() {
+        let data = per_record_gzip(&[b"1", b"22", b"333"]);
+
+        let total = AtomicUsize::new(0);
+        ParallelGzipReader::new(&data)
+            .batch_size(64)
+            .par_for_each(|record| {
+                total.fetch_add(record.body().len(), Ordering::SeqCst);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(total.into_inner(), 6);
+
+        let result = ParallelGzipReader::new(&data).par_for_each(|_| Err(Error::ReadOverflow));
+        assert!(matches!(result, Err(Error::ReadOverflow)));
+    }
+
+    #[test]
+    fn truncated() {
+        let data = per_record_gzip(&[b"first", b"second"]);
+        let mut iter = ParallelGzipReader::new(&data[..data.len() - 5]).records();
+        assert!(iter.next().unwrap().is_ok());
+        assert!(matches!(iter.next(), Some(Err(Error::ReadData(_)))));
+        assert!(iter.next().is_none());
+
+        let mut iter = ParallelGzipReader::new(b"not gzip data").records();
+        assert!(matches!(iter.next(), Some(Err(Error::ReadData(_)))));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn compress_on_drop() {
+        let record = RecordBuilder::default()
+            .warc_type(RecordType::Resource)
+            .body(b"pending".to_vec())
+            .build()
+            .unwrap();
+
+        let mut data = Vec::new();
+        {
+            let mut writer = ParallelGzipWriter::new(&mut data);
+            writer.write(&record).unwrap();
+        }
+
+        let read = ParallelGzipReader::new(&data)
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].warc_id(), record.warc_id());
+        assert_eq!(read[0].body(), record.body());
+    }
+
+    #[test]
+    fn compress_in_parallel() {
+        let warcinfo = WarcInfo::new()
+            .software("warc-rs")
+            .into_record(None)
+            .unwrap();
+        let records = (0..10)
+            .map(|i| {
+                RecordBuilder::default()
+                    .warc_type(RecordType::Resource)
+                    .body(format!("record {}", i).into_bytes())
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let options = WarcWriterOptions::new().level(1).record_length_hint(true);
+        let mut writer = ParallelGzipWriter::with_options(Vec::new(), options).batch_size(100);
+        writer.set_link_warcinfo(true);
+        writer.write(&warcinfo).unwrap();
+        writer.set_max_body_length(Some(6));
+        for record in records.iter() {
+            writer.write(record).unwrap();
+        }
+        assert_eq!(writer.warcinfo_id(), Some(warcinfo.warc_id()));
+        let data = writer.finish().unwrap();
+
+        // every segment is its own member, so the members can be read in parallel
+        let read = ParallelGzipReader::new(&data)
+            .batch_size(1)
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(read.len(), 1 + 2 * records.len());
+
+        let reader = WarcReader::new(BufReader::new(GzipReader::new(Cursor::new(data)).unwrap()));
+        let reassembled = reader
+            .iter_reassembled_records()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(reassembled.len(), 1 + records.len());
+        for (record, expected) in reassembled.into_iter().skip(1).zip(records.iter()) {
+            assert_eq!(record.record().warc_id(), expected.warc_id());
+            assert_eq!(
+                record.record().header(WarcHeader::WarcInfoID).unwrap(),
+                warcinfo.warc_id()
+            );
+            assert_eq!(record.into_buffered().unwrap().body(), expected.body());
+        }
+    }
+}
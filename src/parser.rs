@@ -1,9 +1,11 @@
 use nom::{
+    branch::alt,
     bytes::streaming::{tag, take, take_while1},
     character::streaming::{line_ending, not_line_ending, space0},
+    combinator::{eof, recognize},
     error::ErrorKind,
-    multi::many1,
-    sequence::tuple,
+    multi::{many0, many1},
+    sequence::{terminated, tuple},
     IResult,
 };
 use std::str;
@@ -127,9 +129,61 @@ pub fn record(input: &[u8]) -> IResult<&[u8], (&str, Vec<(&str, &[u8])>, &[u8])>
     Ok((input, (headers.0, headers.1, body)))
 }
 
+fn field(input: &[u8]) -> IResult<&[u8], (&[u8], &[u8])> {
+    use nom::bytes::complete as bytes;
+    use nom::character::complete as character;
+
+    let (input, (token, _, _, _, value, _)) = tuple((
+        bytes::take_while1(is_header_token_char),
+        character::space0,
+        bytes::tag(":"),
+        character::space0,
+        recognize(tuple((
+            character::not_line_ending,
+            many0(tuple((
+                character::line_ending,
+                character::space1,
+                character::not_line_ending,
+            ))),
+        ))),
+        alt((character::line_ending, eof)),
+    ))(input)?;
+
+    Ok((input, (token, value)))
+}
+
+/// Parse an `application/warc-fields` block, such as the body of a `warcinfo` record.
+///
+/// Unlike `headers`, the input is expected to be complete; the last field does not need to be
+/// terminated by a line ending. Values folded over continuation lines (lines starting with a space
+/// or tab) are returned as-is, including the folding line endings.
+pub fn warc_fields(input: &[u8]) -> IResult<&[u8], Vec<(&str, &[u8])>> {
+    let (input, fields) = terminated(
+        many0(field),
+        tuple((many0(nom::character::complete::line_ending), eof)),
+    )(input)?;
+
+    let mut warc_fields: Vec<(&str, &[u8])> = Vec::with_capacity(fields.len());
+    for (token, value) in fields {
+        let token_str = match str::from_utf8(token) {
+            Err(_) => {
+                return Err(nom::Err::Error(nom::error::Error::new(
+                    input,
+                    ErrorKind::Verify,
+                )));
+            }
+            Ok(token) => token,
+        };
+
+        warc_fields.push((token_str, value));
+    }
+
+    Ok((input, warc_fields))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{header, headers, record, version};
+    use super::{header, headers, record, version, warc_fields};
     use nom::error::ErrorKind;
     use nom::Err;
     use nom::Needed;
@@ -241,4 +295,38 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn parse_warc_fields() {
+        let raw = b"\
+            software: warc-rs\r\n\
+            format: WARC File Format 1.0\r\n\
+            isPartOf: a-crawl\
+        ";
+
+        let expected_fields: Vec<(&str, &[u8])> = vec![
+            ("software", b"warc-rs"),
+            ("format", b"WARC File Format 1.0"),
+            ("isPartOf", b"a-crawl"),
+        ];
+
+        assert_eq!(warc_fields(&raw[..]), Ok((&b""[..], expected_fields)));
+        assert_eq!(warc_fields(&b""[..]), Ok((&b""[..], vec![])));
+
+        let folded = b"\
+            outlink: http://example.com/ L a/@href\r\n\
+            description: a value folded\r\n\
+            \tover two lines\r\n\
+            outlink: http://example.com/style.css E link/@href\r\n\
+        ";
+
+        let expected_fields: Vec<(&str, &[u8])> = vec![
+            ("outlink", b"http://example.com/ L a/@href"),
+            ("description", b"a value folded\r\n\tover two lines"),
+            ("outlink", b"http://example.com/style.css E link/@href"),
+        ];
+
+        assert_eq!(warc_fields(&folded[..]), Ok((&b""[..], expected_fields)));
+        assert!(warc_fields(&b"not a field\r\n"[..]).is_err());
+    }
 }
@@ -0,0 +1,571 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use crate::header::WarcHeader;
+use crate::record_type::RecordType;
+use crate::{BufferedBody, EmptyBody, Error, Record};
+
+/// A segment body which was written to a temporary file.
+///
+/// The file is removed when the segment is dropped.
+struct SpilledSegment {
+    file: Option<fs::File>,
+    path: PathBuf,
+}
+
+impl SpilledSegment {
+    fn create(dir: &Path, body: &[u8]) -> io::Result<Self> {
+        let path = dir.join(format!("{}.segment", Uuid::new_v4()));
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        file.write_all(body)?;
+        file.seek(SeekFrom::Start(0))?;
+
+        Ok(SpilledSegment {
+            file: Some(file),
+            path,
+        })
+    }
+}
+
+impl Read for SpilledSegment {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.file {
+            Some(ref mut file) => file.read(buf),
+            None => Ok(0),
+        }
+    }
+}
+
+impl Drop for SpilledSegment {
+    fn drop(&mut self) {
+        // NB: the file must be closed before it can be removed on some platforms
+        self.file.take();
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+enum SegmentBody {
+    Memory(Cursor<Vec<u8>>),
+    Spilled(SpilledSegment),
+}
+
+impl Read for SegmentBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            SegmentBody::Memory(cursor) => cursor.read(buf),
+            SegmentBody::Spilled(spilled) => spilled.read(buf),
+        }
+    }
+}
+
+/// A record read from one or more segments, with a body chained across all of them.
+///
+/// Records which were not segmented are returned as a single segment.
+///
+/// The headers are those of the first segment, without the `WARC-Segment-Number` header. The
+/// body is read through the `Read` trait, or collected with `into_buffered`.
+pub struct ReassembledRecord {
+    record: Record<EmptyBody>,
+    content_length: u64,
+    segment_count: usize,
+    segments: VecDeque<SegmentBody>,
+}
+
+impl ReassembledRecord {
+    /// Return the headers of the reassembled record.
+    pub fn record(&self) -> &Record<EmptyBody> {
+        &self.record
+    }
+
+    /// Return the length of the body of the reassembled record.
+    ///
+    /// This is the sum of the lengths of all segments.
+    pub fn content_length(&self) -> u64 {
+        self.content_length
+    }
+
+    /// Return the number of segments this record was read from.
+    pub fn segment_count(&self) -> usize {
+        self.segment_count
+    }
+
+    /// Return a record with a buffered body by collecting the remaining body.
+    pub fn into_buffered(mut self) -> io::Result<Record<BufferedBody>> {
+        let mut body = Vec::with_capacity(self.content_length as usize);
+        self.read_to_end(&mut body)?;
+
+        Ok(self.record.add_body(body))
+    }
+}
+
+impl Read for ReassembledRecord {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(segment) = self.segments.front_mut() {
+            match segment.read(buf)? {
+                0 if !buf.is_empty() => {
+                    self.segments.pop_front();
+                }
+                n => return Ok(n),
+            }
+        }
+
+        Ok(0)
+    }
+}
+
+/// How far past the number of segments read a segment number may be.
+///
+/// Segments may arrive out of order, but a number far past the segments read is not plausible, and
+/// would make reporting the missing segments unbounded.
+const MAX_SEGMENT_GAP: u64 = 1_024;
+
+#[derive(Default)]
+struct PendingRecord {
+    origin: Option<Record<EmptyBody>>,
+    segments: BTreeMap<u64, (u64, SegmentBody)>,
+    last_segment: Option<u64>,
+    total_length: Option<u64>,
+    memory_len: usize,
+}
+
+impl PendingRecord {
+    fn is_complete(&self) -> bool {
+        self.origin.is_some()
+            && self
+                .last_segment
+                .is_some_and(|last| self.segments.keys().copied().eq(1..=last))
+    }
+
+    fn missing_segments(&self) -> Vec<u64> {
+        let highest = self.segments.keys().next_back().copied().unwrap_or(0);
+        let last = self
+            .last_segment
+            .unwrap_or_else(|| highest.saturating_add(1));
+        (1..=last)
+            .filter(|n| !self.segments.contains_key(n))
+            .take(MAX_SEGMENT_GAP as usize)
+            .collect()
+    }
+}
+
+/// An iterator adapter which reassembles segmented records.
+///
+/// Records carrying a `WARC-Segment-Number` header are held back until all of their segments
+/// were read, and then returned as a single record. Records which are not segmented are returned
+/// as they are read.
+///
+/// Segments are buffered in memory. When a spill directory is set, segment bodies are instead
+/// written to temporary files in that directory once the buffered segments exceed a threshold.
+pub struct SegmentedRecordIter<I> {
+    inner: I,
+    pending: HashMap<String, PendingRecord>,
+    spill_dir: Option<PathBuf>,
+    spill_threshold: usize,
+    memory_len: usize,
+}
+
+impl<I> SegmentedRecordIter<I>
+where
+    I: Iterator<Item = Result<Record<BufferedBody>, Error>>,
+{
+    /// Create a new adapter over an iterator of records.
+    pub fn new(inner: I) -> Self {
+        SegmentedRecordIter {
+            inner,
+            pending: HashMap::new(),
+            spill_dir: None,
+            spill_threshold: usize::MAX,
+            memory_len: 0,
+        }
+    }
+
+    /// Write segment bodies to temporary files in `dir` once more than `threshold` bytes of
+    /// segments are buffered in memory.
+    pub fn spill_to<P: Into<PathBuf>>(mut self, dir: P, threshold: usize) -> Self {
+        self.spill_dir = Some(dir.into());
+        self.spill_threshold = threshold;
+
+        self
+    }
+
+    fn parse_number(record: &Record<BufferedBody>, header: WarcHeader) -> Result<u64, Error> {
+        record
+            .header(header.clone())
+            .ok_or_else(|| Error::MissingHeader(header.clone()))?
+            .parse::<u64>()
+            .map_err(|_| Error::MalformedHeader(header, "not an integer".to_string()))
+    }
+
+    fn check_number(
+        &self,
+        origin_id: &str,
+        number: u64,
+        is_origin: bool,
+        is_last: bool,
+    ) -> Result<(), Error> {
+        let malformed = |reason: &str| {
+            Err(Error::MalformedHeader(
+                WarcHeader::SegmentNumber,
+                reason.to_string(),
+            ))
+        };
+
+        if number == 0 {
+            return malformed("segment numbers start at 1");
+        }
+        if is_origin != (number == 1) {
+            return malformed("only the origin segment may be numbered 1");
+        }
+        let pending = self.pending.get(origin_id);
+        let read = pending.map_or(0, |pending| pending.segments.len() as u64);
+        if number > read + 1 + MAX_SEGMENT_GAP {
+            return malformed("too far past the segments read");
+        }
+        let pending = match pending {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        if pending.segments.contains_key(&number) {
+            return malformed("duplicate segment number");
+        }
+        let last = if is_last {
+            Some(number)
+        } else {
+            pending.last_segment
+        };
+        if let Some(last) = last {
+            let highest = pending.segments.keys().next_back().map_or(number, |n| *n);
+            if number > last || highest > last {
+                return malformed("beyond the last segment");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_segment(&mut self, record: Record<BufferedBody>) -> Result<Option<String>, Error> {
+        let number = Self::parse_number(&record, WarcHeader::SegmentNumber)?;
+        let is_origin = record.warc_type() != &RecordType::Continuation;
+        let origin_id = if is_origin {
+            record.warc_id().to_string()
+        } else {
+            record
+                .header(WarcHeader::SegmentOriginID)
+                .ok_or(Error::MissingHeader(WarcHeader::SegmentOriginID))?
+                .into_owned()
+        };
+        let total_length = if record.header(WarcHeader::SegmentTotalLength).is_some() {
+            Some(Self::parse_number(&record, WarcHeader::SegmentTotalLength)?)
+        } else {
+            None
+        };
+        if let Err(e) = self.check_number(&origin_id, number, is_origin, total_length.is_some()) {
+            if let Some(pending) = self.pending.remove(&origin_id) {
+                self.memory_len -= pending.memory_len;
+            }
+            return Err(e);
+        }
+
+        let (mut headers, body) = record.into_raw_parts();
+        let spill = match self.spill_dir {
+            Some(ref dir) if self.memory_len + body.len() > self.spill_threshold => Some(dir),
+            _ => None,
+        };
+        let body_len = body.len();
+        let segment = match spill {
+            Some(dir) => {
+                SegmentBody::Spilled(SpilledSegment::create(dir, &body).map_err(Error::ReadData)?)
+            }
+            None => SegmentBody::Memory(Cursor::new(body)),
+        };
+
+        let pending = self.pending.entry(origin_id.clone()).or_default();
+        if let SegmentBody::Memory(_) = segment {
+            pending.memory_len += body_len;
+            self.memory_len += body_len;
+        }
+        pending.segments.insert(number, (body_len as u64, segment));
+        if total_length.is_some() {
+            pending.last_segment = Some(number);
+            pending.total_length = total_length;
+        }
+        if is_origin {
+            headers.as_mut().remove(&WarcHeader::SegmentNumber);
+            pending.origin = Some(Record::<EmptyBody>::try_from(headers)?);
+        }
+
+        if pending.is_complete() {
+            Ok(Some(origin_id))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn take_complete(&mut self, origin_id: &str) -> Result<ReassembledRecord, Error> {
+        let pending = self.pending.remove(origin_id).unwrap();
+        self.memory_len -= pending.memory_len;
+
+        let content_length: u64 = pending.segments.values().map(|(len, _)| len).sum();
+        if pending.total_length != Some(content_length) {
+            return Err(Error::MalformedHeader(
+                WarcHeader::SegmentTotalLength,
+                "does not match the length of the segments".to_string(),
+            ));
+        }
+
+        Ok(ReassembledRecord {
+            record: pending.origin.unwrap(),
+            content_length,
+            segment_count: pending.segments.len(),
+            segments: pending
+                .segments
+                .into_iter()
+                .map(|(_, (_, segment))| segment)
+                .collect(),
+        })
+    }
+}
+
+impl<I> Iterator for SegmentedRecordIter<I>
+where
+    I: Iterator<Item = Result<Record<BufferedBody>, Error>>,
+{
+    type Item = Result<ReassembledRecord, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let record = match self.inner.next() {
+                None => break,
+                Some(Err(e)) => return Some(Err(e)),
+                Some(Ok(record)) => record,
+            };
+
+            let is_segment = record.warc_type() == &RecordType::Continuation
+                || record.header(WarcHeader::SegmentNumber).is_some();
+            if !is_segment {
+                let content_length = record.content_length();
+                let (headers, body) = record.into_raw_parts();
+                let record = match Record::<EmptyBody>::try_from(headers) {
+                    Err(e) => return Some(Err(e)),
+                    Ok(record) => record,
+                };
+                return Some(Ok(ReassembledRecord {
+                    record,
+                    content_length,
+                    segment_count: 1,
+                    segments: vec![SegmentBody::Memory(Cursor::new(body))].into(),
+                }));
+            }
+
+            match self.add_segment(record) {
+                Err(e) => return Some(Err(e)),
+                Ok(Some(origin_id)) => return Some(self.take_complete(&origin_id)),
+                Ok(None) => (),
+            }
+        }
+
+        let origin_id = self.pending.keys().next()?.clone();
+        let pending = self.pending.remove(&origin_id).unwrap();
+        self.memory_len -= pending.memory_len;
+        Some(Err(Error::MissingSegments(
+            origin_id,
+            pending.missing_segments(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SegmentedRecordIter;
+    use crate::{Error, RecordBuilder, RecordType, WarcHeader};
+
+    use chrono::prelude::*;
+    use std::io::Read;
+
+    fn date() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2020, 7, 8, 2, 52, 55).unwrap()
+    }
+
+    #[test]
+    fn unsegmented() {
+        let record = RecordBuilder::default()
+            .date(date())
+            .body(b"12345".to_vec())
+            .build()
+            .unwrap();
+
+        let mut iter = SegmentedRecordIter::new(vec![Ok(record.clone())].into_iter());
+        let reassembled = iter.next().unwrap().unwrap();
+        assert_eq!(reassembled.segment_count(), 1);
+        assert_eq!(reassembled.content_length(), 5);
+        assert_eq!(reassembled.into_buffered().unwrap(), record);
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn reassemble() {
+        let record = RecordBuilder::default()
+            .date(date())
+            .warc_type(RecordType::Response)
+            .header(WarcHeader::TargetURI, "http://example.com/")
+            .body(b"0123456789".to_vec())
+            .build()
+            .unwrap();
+        let other = RecordBuilder::default()
+            .date(date())
+            .body(b"other".to_vec())
+            .build()
+            .unwrap();
+
+        let mut segments = record.clone().into_segments(3);
+        assert_eq!(segments.len(), 4);
+        // segments may be interleaved with other records, and arrive out of order
+        segments.insert(2, other.clone());
+        segments.swap(3, 4);
+
+        let mut iter = SegmentedRecordIter::new(segments.into_iter().map(Ok));
+        let first = iter.next().unwrap().unwrap();
+        assert_eq!(first.into_buffered().unwrap(), other);
+
+        let mut reassembled = iter.next().unwrap().unwrap();
+        assert_eq!(reassembled.segment_count(), 4);
+        assert_eq!(reassembled.content_length(), 10);
+        assert_eq!(reassembled.record().warc_id(), record.warc_id());
+        assert!(reassembled
+            .record()
+            .header(WarcHeader::SegmentNumber)
+            .is_none());
+
+        let mut body = Vec::new();
+        reassembled.read_to_end(&mut body).unwrap();
+        assert_eq!(body, b"0123456789");
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn spill_to_disk() {
+        let dir = std::env::temp_dir();
+        let record = RecordBuilder::default()
+            .date(date())
+            .body(b"0123456789".to_vec())
+            .build()
+            .unwrap();
+
+        let segments = record.clone().into_segments(4);
+        let mut iter = SegmentedRecordIter::new(segments.into_iter().map(Ok)).spill_to(&dir, 4);
+        let reassembled = iter.next().unwrap().unwrap();
+        assert_eq!(reassembled.into_buffered().unwrap(), record);
+    }
+
+    #[test]
+    fn missing_segment() {
+        let record = RecordBuilder::default()
+            .date(date())
+            .body(b"0123456789".to_vec())
+            .build()
+            .unwrap();
+
+        let mut segments = record.clone().into_segments(4);
+        segments.remove(1);
+
+        let mut iter = SegmentedRecordIter::new(segments.into_iter().map(Ok));
+        match iter.next() {
+            Some(Err(Error::MissingSegments(id, missing))) => {
+                assert_eq!(id, record.warc_id());
+                assert_eq!(missing, vec![2]);
+            }
+            _ => panic!("expected missing segments"),
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn wrong_total_length() {
+        let record = RecordBuilder::default()
+            .date(date())
+            .body(b"0123456789".to_vec())
+            .build()
+            .unwrap();
+
+        let mut segments = record.into_segments(4);
+        segments[2]
+            .set_header(WarcHeader::SegmentTotalLength, "11")
+            .unwrap();
+
+        let mut iter = SegmentedRecordIter::new(segments.into_iter().map(Ok));
+        assert!(iter.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn extra_segment_number() {
+        let record = RecordBuilder::default()
+            .date(date())
+            .body(b"0123456789".to_vec())
+            .build()
+            .unwrap();
+
+        // the three segments are numbered 1, 7 and 3, with the last segment claiming to be #3
+        let mut segments = record.into_segments(4);
+        segments[1]
+            .set_header(WarcHeader::SegmentNumber, "7")
+            .unwrap();
+
+        let mut iter = SegmentedRecordIter::new(segments.into_iter().map(Ok));
+        match iter.next() {
+            Some(Err(Error::MalformedHeader(WarcHeader::SegmentNumber, _))) => (),
+            _ => panic!("expected a malformed segment number"),
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn duplicate_segment_number() {
+        let record = RecordBuilder::default()
+            .date(date())
+            .body(b"0123456789".to_vec())
+            .build()
+            .unwrap();
+
+        let mut segments = record.into_segments(4);
+        segments[2]
+            .set_header(WarcHeader::SegmentNumber, "2")
+            .unwrap();
+
+        let mut iter = SegmentedRecordIter::new(segments.into_iter().map(Ok));
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn implausible_segment_numbers() {
+        let record = RecordBuilder::default()
+            .date(date())
+            .body(b"0123456789".to_vec())
+            .build()
+            .unwrap();
+
+        for number in ["0", "18446744073709551615"] {
+            let mut continuation = record.clone().into_segments(4).remove(2);
+            continuation
+                .set_header(WarcHeader::SegmentNumber, number)
+                .unwrap();
+
+            let mut iter = SegmentedRecordIter::new(vec![Ok(continuation)].into_iter());
+            match iter.next() {
+                Some(Err(Error::MalformedHeader(WarcHeader::SegmentNumber, _))) => (),
+                _ => panic!("expected a malformed segment number"),
+            }
+            assert!(iter.next().is_none());
+        }
+    }
+}
@@ -3,10 +3,14 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt;
 use std::io::Read;
+use std::net::IpAddr;
+use std::sync::Arc;
 
-use uuid::Uuid;
+use url::Url;
 
 use crate::header::WarcHeader;
+use crate::media_type::MediaType;
+use crate::record_id::{RecordId, RecordIdGenerator};
 use crate::record_type::RecordType;
 use crate::truncated_type::TruncatedType;
 use crate::Error as WarcError;
@@ -32,10 +36,19 @@ mod streaming_trait {
     }
 
     /// An associated type indicating the body is streamed from a reader.
-    pub struct StreamingBody<'t, T: Read + 't>(&'t mut T, &'t mut u64);
+    pub struct StreamingBody<'t, T: Read + 't>(&'t mut T, &'t mut u64, Option<&'t mut Vec<u8>>);
     impl<'t, T: Read + 't> StreamingBody<'t, T> {
         pub(crate) fn new(stream: &'t mut T, max_len: &'t mut u64) -> StreamingBody<'t, T> {
-            StreamingBody(stream, max_len)
+            StreamingBody(stream, max_len, None)
+        }
+
+        /// Create a body which also appends the bytes read to a buffer.
+        pub(crate) fn with_capture(
+            stream: &'t mut T,
+            max_len: &'t mut u64,
+            capture: &'t mut Vec<u8>,
+        ) -> StreamingBody<'t, T> {
+            StreamingBody(stream, max_len, Some(capture))
         }
 
         pub(crate) fn len(&self) -> u64 {
@@ -51,9 +64,13 @@ mod streaming_trait {
     impl<'t, T: Read + 't> Read for StreamingBody<'t, T> {
         fn read(&mut self, data: &mut [u8]) -> std::io::Result<usize> {
             let max_read = std::cmp::min(data.len(), *self.1 as usize);
-            self.0.read(&mut data[..max_read]).inspect(|&n| {
-                *self.1 -= n as u64;
-            })
+            let n = self.0.read(&mut data[..max_read])?;
+            *self.1 -= n as u64;
+            if let Some(capture) = self.2.as_mut() {
+                capture.extend_from_slice(&data[..n]);
+            }
+
+            Ok(n)
         }
     }
 
@@ -169,6 +186,8 @@ pub struct RecordBuilder {
     value: Record<BufferedBody>,
     broken_headers: HashMap<WarcHeader, Vec<u8>>,
     last_error: Option<WarcError>,
+    id_generator: Option<Arc<dyn RecordIdGenerator + Send + Sync>>,
+    has_explicit_id: bool,
 }
 
 // HACK: std::io::Error doesn't implement Clone, this is the next best thing
@@ -178,16 +197,24 @@ impl Clone for RecordBuilder {
         let err: Option<&WarcError> = self.last_error.as_ref();
         let last_error: Option<WarcError> = err.map(|err| match err {
             WarcError::ReadData(e) => WarcError::ReadData(std::io::Error::from(e.kind())),
+            WarcError::WriteData(e) => WarcError::WriteData(std::io::Error::from(e.kind())),
             WarcError::ParseHeaders(e) => WarcError::ParseHeaders(e.clone()),
             WarcError::MissingHeader(e) => WarcError::MissingHeader(e.clone()),
             WarcError::MalformedHeader(h, e) => WarcError::MalformedHeader(h.clone(), e.clone()),
             WarcError::ReadOverflow => WarcError::ReadOverflow,
             WarcError::UnexpectedEOB => WarcError::UnexpectedEOB,
+            WarcError::MissingSegments(id, missing) => {
+                WarcError::MissingSegments(id.clone(), missing.clone())
+            }
+            WarcError::ParseIndex(r) => WarcError::ParseIndex(r.clone()),
+            WarcError::ParseTimestamp(t) => WarcError::ParseTimestamp(t.clone()),
         });
         RecordBuilder {
             value: self.value.clone(),
             broken_headers: self.broken_headers.clone(),
             last_error,
+            id_generator: self.id_generator.clone(),
+            has_explicit_id: self.has_explicit_id,
         }
     }
 }
@@ -199,7 +226,8 @@ impl Clone for RecordBuilder {
 /// The associated type `T` indicates the representation of this record's body.
 ///
 /// A record is guaranteed to be valid according to the specification it conforms to, except:
-/// * The validity of the WARC-Record-ID header is not checked
+/// * The validity of the WARC-Record-ID header is not checked when reading records, only by
+///   `RecordBuilder::build`
 /// * Date information not in the UTC timezone will be silently converted to UTC
 ///
 /// Use the `Display` trait to generate the formatted representation.
@@ -262,7 +290,7 @@ impl<T: BodyKind> Record<T> {
     /// The current implementation generates random values based on UUID version 4.
     ///
     pub fn generate_record_id() -> String {
-        format!("<{}>", Uuid::new_v4().to_urn())
+        RecordId::generate().to_string()
     }
 
     fn parse_content_length(len: &str) -> Result<u64, WarcError> {
@@ -274,6 +302,43 @@ impl<T: BodyKind> Record<T> {
         })
     }
 
+    /// Parse a WARC-Target-URI value, tolerating the forms found in existing files.
+    ///
+    /// Angle brackets, as written by some WARC 1.0 tools, are removed. Non-ASCII characters are
+    /// percent-encoded, or converted to Punycode in host names, and a `%` which does not start a
+    /// valid escape is itself escaped.
+    pub(crate) fn parse_target_uri(uri: &str) -> Option<Url> {
+        let uri = uri.trim();
+        let uri = uri
+            .strip_prefix('<')
+            .and_then(|uri| uri.strip_suffix('>'))
+            .unwrap_or(uri);
+
+        let bytes = uri.as_bytes();
+        let mut escaped = String::with_capacity(uri.len());
+        for (i, c) in uri.char_indices() {
+            let is_escape = bytes.len() > i + 2
+                && bytes[i + 1].is_ascii_hexdigit()
+                && bytes[i + 2].is_ascii_hexdigit();
+            if c == '%' && !is_escape {
+                escaped.push_str("%25");
+            } else {
+                escaped.push(c);
+            }
+        }
+
+        Url::parse(&escaped).ok()
+    }
+
+    fn parse_ip_address(address: &str) -> Result<IpAddr, WarcError> {
+        address.trim().parse().map_err(|_| {
+            WarcError::MalformedHeader(
+                WarcHeader::IPAddress,
+                "not an IPv4 or IPv6 address".to_string(),
+            )
+        })
+    }
+
     fn parse_record_date(date: &str) -> Result<DateTime<Utc>, WarcError> {
         DateTime::parse_from_rfc3339(date)
             .map_err(|_| {
@@ -290,6 +355,11 @@ impl<T: BodyKind> Record<T> {
         &self.headers.version
     }
 
+    /// Return the date of this record as a 14-digit wayback timestamp.
+    pub fn timestamp(&self) -> String {
+        crate::timestamp::format(&self.record_date)
+    }
+
     /// Set the WARC version string of this record.
     pub fn set_warc_version<S: Into<String>>(&mut self, id: S) {
         self.headers.version = id.into();
@@ -300,6 +370,11 @@ impl<T: BodyKind> Record<T> {
         &self.record_id
     }
 
+    /// Parse the WARC-Record-ID header for this record.
+    pub fn record_id(&self) -> Result<RecordId, WarcError> {
+        RecordId::parse(&self.record_id)
+    }
+
     /// Set the WARC-Record-ID header for this record.
     ///
     /// Note that this value is **not** checked for validity.
@@ -342,6 +417,117 @@ impl<T: BodyKind> Record<T> {
         self.truncated_type = None;
     }
 
+    /// Return the WARC-Target-URI header for this record, if it is present and a URI or IRI.
+    ///
+    /// The value is normalized: see `raw_target_uri` for the value as it was written.
+    pub fn target_uri(&self) -> Option<Url> {
+        self.raw_target_uri()
+            .and_then(|uri| Record::<T>::parse_target_uri(uri))
+    }
+
+    /// Return the WARC-Target-URI header for this record, as it was written.
+    pub fn raw_target_uri(&self) -> Option<&str> {
+        self.headers
+            .as_ref()
+            .get(&WarcHeader::TargetURI)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+
+    /// Set the WARC-Target-URI header for this record.
+    pub fn set_target_uri(&mut self, uri: &Url) {
+        self.headers
+            .as_mut()
+            .insert(WarcHeader::TargetURI, uri.as_str().as_bytes().to_vec());
+    }
+
+    /// Return the Content-Type header for this record, if it is present and well-formed.
+    pub fn content_type(&self) -> Option<MediaType> {
+        self.parsed_header(WarcHeader::ContentType)
+            .and_then(|value| MediaType::parse(value).ok())
+    }
+
+    /// Set the Content-Type header for this record.
+    pub fn set_content_type(&mut self, content_type: &MediaType) {
+        self.headers.as_mut().insert(
+            WarcHeader::ContentType,
+            content_type.to_string().into_bytes(),
+        );
+    }
+
+    /// Return the WARC-Identified-Payload-Type header for this record, if it is present and
+    /// well-formed.
+    pub fn identified_payload_type(&self) -> Option<MediaType> {
+        self.parsed_header(WarcHeader::IdentifiedPayloadType)
+            .and_then(|value| MediaType::parse(value).ok())
+    }
+
+    /// Return `true` if the block of this record is an HTTP response, with a Content-Type of
+    /// `application/http`.
+    ///
+    /// The `msgtype` parameter says whether the message is a request or a response. If it is
+    /// missing, response records are assumed to hold responses.
+    pub fn is_http_response(&self) -> bool {
+        self.http_msgtype()
+            .map(|msgtype| match msgtype {
+                Some(msgtype) => msgtype.eq_ignore_ascii_case("response"),
+                None => self.record_type == RecordType::Response,
+            })
+            .unwrap_or(false)
+    }
+
+    /// Return `true` if the block of this record is an HTTP request, with a Content-Type of
+    /// `application/http`.
+    ///
+    /// The `msgtype` parameter says whether the message is a request or a response. If it is
+    /// missing, request records are assumed to hold requests.
+    pub fn is_http_request(&self) -> bool {
+        self.http_msgtype()
+            .map(|msgtype| match msgtype {
+                Some(msgtype) => msgtype.eq_ignore_ascii_case("request"),
+                None => self.record_type == RecordType::Request,
+            })
+            .unwrap_or(false)
+    }
+
+    fn http_msgtype(&self) -> Option<Option<String>> {
+        self.content_type()
+            .filter(|content_type| content_type.is("application", "http"))
+            .map(|content_type| content_type.param("msgtype").map(Into::into))
+    }
+
+    /// Return `true` if the block of this record is a DNS response, with a Content-Type of
+    /// `text/dns` as written by most crawlers, or `application/dns-message`.
+    pub fn is_dns(&self) -> bool {
+        self.content_type()
+            .map(|content_type| {
+                content_type.is("text", "dns") || content_type.is("application", "dns-message")
+            })
+            .unwrap_or(false)
+    }
+
+    fn parsed_header(&self, header: WarcHeader) -> Option<&str> {
+        self.headers
+            .as_ref()
+            .get(&header)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+
+    /// Return the WARC-IP-Address header for this record, if it is present and well-formed.
+    pub fn ip_address(&self) -> Option<IpAddr> {
+        self.headers
+            .as_ref()
+            .get(&WarcHeader::IPAddress)
+            .and_then(|value| std::str::from_utf8(value).ok())
+            .and_then(|value| Record::<T>::parse_ip_address(value).ok())
+    }
+
+    /// Set the WARC-IP-Address header for this record.
+    pub fn set_ip_address(&mut self, address: IpAddr) {
+        self.headers
+            .as_mut()
+            .insert(WarcHeader::IPAddress, address.to_string().into_bytes());
+    }
+
     /// Return the WARC header requested if present in this record, or `None`.
     pub fn header(&self, header: WarcHeader) -> Option<Cow<'_, str>> {
         match &header {
@@ -397,7 +583,23 @@ impl<T: BodyKind> Record<T> {
             WarcHeader::Truncated => {
                 let old_type = self.truncated_type.take();
                 self.truncated_type = Some(TruncatedType::from(&value));
-                Ok(old_type.map(|old| (Cow::Owned(old.to_string()))))
+                Ok(old_type.map(|old| Cow::Owned(old.to_string())))
+            }
+            WarcHeader::ContentType | WarcHeader::IdentifiedPayloadType => {
+                MediaType::parse_header(header.clone(), &value)?;
+                Ok(self
+                    .headers
+                    .as_mut()
+                    .insert(header, Vec::from(value))
+                    .map(|v| Cow::Owned(String::from_utf8(v).unwrap())))
+            }
+            WarcHeader::IPAddress => {
+                Record::<T>::parse_ip_address(&value)?;
+                Ok(self
+                    .headers
+                    .as_mut()
+                    .insert(header, Vec::from(value))
+                    .map(|v| Cow::Owned(String::from_utf8(v).unwrap())))
             }
             WarcHeader::ContentLength => {
                 if Record::<T>::parse_content_length(&value)? != self.body.content_length() {
@@ -453,6 +655,14 @@ impl Record<EmptyBody> {
         stream: &'r mut R,
         len: &'r mut u64,
     ) -> std::io::Result<Record<StreamingBody<'r, R>>> {
+        Ok(self.add_streaming_body(StreamingBody::new(stream, len)))
+    }
+
+    /// Add a streaming body to this record.
+    pub(crate) fn add_streaming_body<'r, R: Read + 'r>(
+        self,
+        body: StreamingBody<'r, R>,
+    ) -> Record<StreamingBody<'r, R>> {
         let Record {
             headers,
             record_date,
@@ -462,14 +672,14 @@ impl Record<EmptyBody> {
             ..
         } = self;
 
-        Ok(Record {
+        Record {
             headers,
             record_date,
             record_id,
             record_type,
             truncated_type,
-            body: StreamingBody::new(stream, len),
-        })
+            body,
+        }
     }
 }
 
@@ -557,6 +767,84 @@ impl Record<BufferedBody> {
 
         (headers, body.0)
     }
+
+    /// Split this record into segments whose bodies are no longer than `max_body_len` bytes.
+    ///
+    /// If the body of this record fits, the record is returned unchanged. Otherwise, the first
+    /// segment keeps the headers of this record and receives `WARC-Segment-Number: 1`. It is
+    /// followed by `continuation` records, each with a newly generated record ID, the date of
+    /// this record, its `WARC-Target-URI` and `WARC-Warcinfo-ID` headers if present, a
+    /// `WARC-Segment-Origin-ID` referencing the first segment, and an increasing
+    /// `WARC-Segment-Number`. The last segment also carries `WARC-Segment-Total-Length`.
+    ///
+    /// A `WARC-Block-Digest` header is dropped from the first segment, as it would describe the
+    /// whole block rather than the segment.
+    ///
+    /// The segments may be written by different writers, for instance when an output file is
+    /// rotated between segments.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_body_len` is zero.
+    pub fn into_segments(mut self, max_body_len: usize) -> Vec<Record<BufferedBody>> {
+        assert!(max_body_len > 0, "segments must have a non-zero length");
+        if self.body.0.len() <= max_body_len {
+            return vec![self];
+        }
+
+        let total_len = self.body.0.len();
+        let body = std::mem::take(&mut self.body.0);
+        let mut chunks = body.chunks(max_body_len).map(|chunk| chunk.to_vec());
+        let segment_count = total_len.div_ceil(max_body_len);
+
+        let carried_headers: Vec<(WarcHeader, Vec<u8>)> =
+            [WarcHeader::TargetURI, WarcHeader::WarcInfoID]
+                .iter()
+                .filter_map(|h| self.headers.as_ref().get(h).map(|v| (h.clone(), v.clone())))
+                .collect();
+
+        self.headers.as_mut().remove(&WarcHeader::BlockDigest);
+        self.headers
+            .as_mut()
+            .insert(WarcHeader::SegmentNumber, b"1".to_vec());
+        self.body = BufferedBody(chunks.next().unwrap());
+
+        let origin_id = self.record_id.clone();
+        let mut segments = Vec::with_capacity(segment_count);
+        segments.push(self);
+
+        for (i, chunk) in chunks.enumerate() {
+            let segment_number = i + 2;
+            let mut headers: HashMap<WarcHeader, Vec<u8>> =
+                carried_headers.iter().cloned().collect();
+            headers.insert(WarcHeader::SegmentOriginID, origin_id.clone().into_bytes());
+            headers.insert(
+                WarcHeader::SegmentNumber,
+                segment_number.to_string().into_bytes(),
+            );
+            if segment_number == segment_count {
+                headers.insert(
+                    WarcHeader::SegmentTotalLength,
+                    total_len.to_string().into_bytes(),
+                );
+            }
+
+            let first = &segments[0];
+            segments.push(Record {
+                headers: RawRecordHeader {
+                    version: first.headers.version.clone(),
+                    headers,
+                },
+                record_date: first.record_date,
+                record_id: Record::<BufferedBody>::generate_record_id(),
+                record_type: RecordType::Continuation,
+                truncated_type: None,
+                body: BufferedBody(chunk),
+            });
+        }
+
+        segments
+    }
 }
 
 impl<'t, T: Read + 't> Record<StreamingBody<'t, T>> {
@@ -593,6 +881,29 @@ impl<'t, T: Read + 't> Record<StreamingBody<'t, T>> {
 
         Ok(empty_record.add_body(buf))
     }
+
+    /// Discard the rest of the body of this record.
+    ///
+    /// The number of bytes skipped is returned upon success.
+    pub fn skip(mut self) -> std::io::Result<u64> {
+        std::io::copy(&mut self.body, &mut std::io::sink())
+    }
+
+    /// Finish reading this record, checking its body was read completely.
+    ///
+    /// # Errors
+    ///
+    /// An error of kind `InvalidData` is returned if some of the body was not read. The rest of
+    /// the body is skipped in either case.
+    pub fn finish(self) -> std::io::Result<()> {
+        match self.skip()? {
+            0 => Ok(()),
+            left => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} bytes of the record body were not read", left),
+            )),
+        }
+    }
 }
 
 impl<'t, T: Read + 't> Read for Record<StreamingBody<'t, T>> {
@@ -671,6 +982,197 @@ impl Clone for Record<BufferedBody> {
     }
 }
 
+#[cfg(feature = "with_serde")]
+mod serde_impls {
+    use std::borrow::Cow;
+    use std::collections::{BTreeMap, HashMap};
+    use std::convert::TryFrom;
+
+    use base64::Engine;
+    use serde::de::Error as _;
+    use serde::ser::SerializeStruct;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{BodyKind, BufferedBody, EmptyBody, RawRecordHeader, Record};
+    use crate::header::WarcHeader;
+    use crate::truncated_type::TruncatedType;
+
+    const BASE64: base64::engine::GeneralPurpose = base64::engine::general_purpose::STANDARD;
+
+    /// How the body of a record is represented when it is serialized.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+    pub enum BodyEncoding {
+        /// The body is a base64 string in the `body` field.
+        #[default]
+        Base64,
+        /// The body is left out. It deserializes as an empty body.
+        Omitted,
+    }
+
+    /// A record borrowed for serialization, with its body in the chosen encoding.
+    ///
+    /// Created by `Record::with_body_encoding`.
+    #[derive(Clone, Copy, Debug)]
+    pub struct EncodedRecord<'r> {
+        record: &'r Record<BufferedBody>,
+        encoding: BodyEncoding,
+    }
+
+    #[derive(Deserialize)]
+    struct RawRepr {
+        version: String,
+        headers: HashMap<WarcHeader, String>,
+        #[serde(default)]
+        body: Option<String>,
+    }
+
+    impl RawRepr {
+        fn into_raw_header(self) -> RawRecordHeader {
+            RawRecordHeader {
+                version: self.version,
+                headers: self
+                    .headers
+                    .into_iter()
+                    .map(|(key, value)| (key, value.into_bytes()))
+                    .collect(),
+            }
+        }
+    }
+
+    fn sorted_headers(headers: &HashMap<WarcHeader, Vec<u8>>) -> BTreeMap<String, Cow<'_, str>> {
+        headers
+            .iter()
+            .map(|(key, value)| (key.to_string(), String::from_utf8_lossy(value)))
+            .collect()
+    }
+
+    impl<T: BodyKind> Record<T> {
+        // NB: the length of an empty body is unknown, so it is only included for other bodies
+        fn serialized_headers(&self, with_length: bool) -> BTreeMap<String, Cow<'_, str>> {
+            let mut headers = sorted_headers(&self.headers.headers);
+            let mut fields = vec![WarcHeader::WarcType, WarcHeader::RecordID, WarcHeader::Date];
+            if with_length {
+                fields.push(WarcHeader::ContentLength);
+            }
+            for field in fields {
+                if let Some(value) = self.header(field.clone()) {
+                    headers.insert(field.to_string(), value);
+                }
+            }
+            if let Some(ref truncated_type) = self.truncated_type {
+                headers.insert(
+                    WarcHeader::Truncated.to_string(),
+                    Cow::Owned(truncated_type.to_string()),
+                );
+            }
+
+            headers
+        }
+    }
+
+    impl Record<EmptyBody> {
+        fn from_repr(repr: RawRepr) -> Result<Record<EmptyBody>, crate::Error> {
+            let mut raw = repr.into_raw_header();
+            raw.headers
+                .entry(WarcHeader::ContentLength)
+                .or_insert_with(|| b"0".to_vec());
+            let truncated = raw.headers.remove(&WarcHeader::Truncated);
+
+            let mut record = Record::<EmptyBody>::try_from(raw)?;
+            record.truncated_type =
+                truncated.map(|value| TruncatedType::from(String::from_utf8_lossy(&value)));
+
+            Ok(record)
+        }
+    }
+
+    impl Record<BufferedBody> {
+        /// Borrow this record for serialization, with its body in the given encoding.
+        ///
+        /// Serializing the record itself encodes the body as base64.
+        pub fn with_body_encoding(&self, encoding: BodyEncoding) -> EncodedRecord<'_> {
+            EncodedRecord {
+                record: self,
+                encoding,
+            }
+        }
+    }
+
+    impl Serialize for RawRecordHeader {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut state = serializer.serialize_struct("RawRecordHeader", 2)?;
+            state.serialize_field("version", &self.version)?;
+            state.serialize_field("headers", &sorted_headers(&self.headers))?;
+            state.end()
+        }
+    }
+
+    impl<'de> Deserialize<'de> for RawRecordHeader {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            RawRepr::deserialize(deserializer).map(RawRepr::into_raw_header)
+        }
+    }
+
+    impl Serialize for EncodedRecord<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let record = self.record;
+            let len = if self.encoding == BodyEncoding::Omitted {
+                2
+            } else {
+                3
+            };
+            let mut state = serializer.serialize_struct("Record", len)?;
+            state.serialize_field("version", record.warc_version())?;
+            state.serialize_field("headers", &record.serialized_headers(true))?;
+            match self.encoding {
+                BodyEncoding::Base64 => {
+                    state.serialize_field("body", &BASE64.encode(record.body()))?
+                }
+                BodyEncoding::Omitted => state.skip_field("body")?,
+            }
+            state.end()
+        }
+    }
+
+    impl Serialize for Record<BufferedBody> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.with_body_encoding(BodyEncoding::Base64)
+                .serialize(serializer)
+        }
+    }
+
+    impl Serialize for Record<EmptyBody> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut state = serializer.serialize_struct("Record", 2)?;
+            state.serialize_field("version", self.warc_version())?;
+            state.serialize_field("headers", &self.serialized_headers(false))?;
+            state.end()
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Record<BufferedBody> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let mut repr = RawRepr::deserialize(deserializer)?;
+            let body = match repr.body.take() {
+                Some(body) => BASE64.decode(body).map_err(D::Error::custom)?,
+                None => Vec::new(),
+            };
+            let record = Record::<EmptyBody>::from_repr(repr).map_err(D::Error::custom)?;
+
+            Ok(record.add_body(body))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Record<EmptyBody> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = RawRepr::deserialize(deserializer)?;
+            Record::<EmptyBody>::from_repr(repr).map_err(D::Error::custom)
+        }
+    }
+}
+#[cfg(feature = "with_serde")]
+pub use serde_impls::{BodyEncoding, EncodedRecord};
+
 impl RecordBuilder {
     /// Set the body of the record under construction.
     pub fn body(mut self, body: Vec<u8>) -> Self {
@@ -686,9 +1188,22 @@ impl RecordBuilder {
         self
     }
 
+    /// Set the record date header of the record under construction from a wayback timestamp.
+    ///
+    /// A prefix of a timestamp sets the first second of the period it names.
+    pub fn timestamp(mut self, timestamp: &str) -> Self {
+        match crate::timestamp::parse(timestamp) {
+            Ok(date) => self.value.set_date(date),
+            Err(e) => self.last_error = Some(e),
+        }
+
+        self
+    }
+
     /// Set the record ID header of the record under construction.
     pub fn warc_id<S: Into<String>>(mut self, id: S) -> Self {
         self.value.set_warc_id(id);
+        self.has_explicit_id = true;
 
         self
     }
@@ -714,8 +1229,30 @@ impl RecordBuilder {
         self
     }
 
+    /// Set the content type header of the record under construction.
+    pub fn content_type(mut self, content_type: &MediaType) -> Self {
+        self.value.set_content_type(content_type);
+
+        self
+    }
+
+    /// Set the target URI header of the record under construction.
+    pub fn target_uri(mut self, uri: &Url) -> Self {
+        self.value.set_target_uri(uri);
+
+        self
+    }
+
+    /// Set the IP address header of the record under construction.
+    pub fn ip_address(mut self, address: IpAddr) -> Self {
+        self.value.set_ip_address(address);
+
+        self
+    }
+
     /// Create or replace an arbitrary header of the record under construction.
     pub fn header<V: Into<Vec<u8>>>(mut self, key: WarcHeader, value: V) -> Self {
+        self.has_explicit_id |= key == WarcHeader::RecordID;
         self.broken_headers.insert(key.clone(), value.into());
 
         let is_ok;
@@ -744,10 +1281,31 @@ impl RecordBuilder {
         self
     }
 
+    /// Generate the record ID of the record under construction with the given generator.
+    ///
+    /// The ID is generated when the record is built, from the headers and body set by then. An ID
+    /// set explicitly, with `warc_id` or as a header, is kept instead.
+    pub fn id_generator<G>(mut self, generator: G) -> Self
+    where
+        G: RecordIdGenerator + Send + Sync + 'static,
+    {
+        self.id_generator = Some(Arc::new(generator));
+
+        self
+    }
+
+    fn generate_id(&mut self) {
+        if let (Some(generator), false) = (&self.id_generator, self.has_explicit_id) {
+            let id = generator.generate(&self.value);
+            self.value.set_warc_id(id);
+        }
+    }
+
     /// Build a raw record header from the data collected in this builder.
     ///
     /// A body set in this builder will be returned raw.
-    pub fn build_raw(self) -> (RawRecordHeader, Vec<u8>) {
+    pub fn build_raw(mut self) -> (RawRecordHeader, Vec<u8>) {
+        self.generate_id();
         let RecordBuilder {
             value,
             broken_headers,
@@ -760,11 +1318,13 @@ impl RecordBuilder {
     }
 
     /// Build a record from the data collected in this builder.
-    pub fn build(self) -> Result<Record<BufferedBody>, WarcError> {
+    pub fn build(mut self) -> Result<Record<BufferedBody>, WarcError> {
+        self.generate_id();
         let RecordBuilder {
             value,
             broken_headers,
             last_error,
+            ..
         } = self;
 
         if let Some(e) = last_error {
@@ -774,6 +1334,17 @@ impl RecordBuilder {
                 broken_headers.is_empty(),
                 "invariant violation: broken headers without last error"
             );
+            RecordId::parse(&value.record_id)?;
+            for header in [
+                WarcHeader::RefersTo,
+                WarcHeader::ConcurrentTo,
+                WarcHeader::WarcInfoID,
+            ] {
+                if let Some(id) = value.headers.as_ref().get(&header) {
+                    let id = String::from_utf8_lossy(id);
+                    RecordId::parse_header(header, &id)?;
+                }
+            }
             Ok(value)
         }
     }
@@ -811,7 +1382,8 @@ mod record_tests {
     fn body() {
         let mut record = Record::<BufferedBody>::default();
         assert_eq!(record.content_length(), 0);
-        assert_eq!(record.body(), &[]);
+        // NB: with serde_json as a dev-dependency, `&[]` alone could also compare to its values
+        assert_eq!(record.body(), &[] as &[u8]);
         record.replace_body(b"hello!!".to_vec());
         assert_eq!(record.content_length(), 7);
         assert_eq!(record.body(), b"hello!!");
@@ -917,6 +1489,74 @@ mod record_tests {
     }
 }
 
+#[cfg(test)]
+mod segment_tests {
+    use crate::header::WarcHeader;
+    use crate::{RecordBuilder, RecordType};
+
+    #[test]
+    fn small_record() {
+        let record = RecordBuilder::default()
+            .body(b"12345".to_vec())
+            .build()
+            .unwrap();
+
+        let segments = record.clone().into_segments(5);
+        assert_eq!(segments, vec![record]);
+    }
+
+    #[test]
+    fn segmented_record() {
+        let record = RecordBuilder::default()
+            .warc_type(RecordType::Response)
+            .header(WarcHeader::TargetURI, "http://example.com/")
+            .header(
+                WarcHeader::BlockDigest,
+                "sha1:UZY6ND6CCHXETFVJD2MSS7ZENMWF7KQ2",
+            )
+            .body(b"1234567890ab".to_vec())
+            .build()
+            .unwrap();
+
+        let segments = record.clone().into_segments(5);
+        assert_eq!(segments.len(), 3);
+
+        let first = &segments[0];
+        assert_eq!(first.warc_id(), record.warc_id());
+        assert_eq!(first.warc_type(), &RecordType::Response);
+        assert_eq!(first.body(), b"12345");
+        assert_eq!(first.header(WarcHeader::SegmentNumber).unwrap(), "1");
+        assert!(first.header(WarcHeader::BlockDigest).is_none());
+        assert!(first.header(WarcHeader::SegmentTotalLength).is_none());
+
+        for (i, segment) in segments.iter().enumerate().skip(1) {
+            assert_ne!(segment.warc_id(), record.warc_id());
+            assert_eq!(segment.warc_type(), &RecordType::Continuation);
+            assert_eq!(segment.date(), record.date());
+            assert_eq!(
+                segment.header(WarcHeader::SegmentOriginID).unwrap(),
+                record.warc_id()
+            );
+            assert_eq!(
+                segment.header(WarcHeader::SegmentNumber).unwrap(),
+                (i + 1).to_string()
+            );
+            assert_eq!(
+                segment.header(WarcHeader::TargetURI).unwrap(),
+                "http://example.com/"
+            );
+        }
+
+        assert_eq!(segments[1].body(), b"67890");
+        assert!(segments[1].header(WarcHeader::SegmentTotalLength).is_none());
+        assert_eq!(segments[2].body(), b"ab");
+        assert_eq!(
+            segments[2].header(WarcHeader::SegmentTotalLength).unwrap(),
+            "12"
+        );
+    }
+}
+
 #[cfg(test)]
 mod raw_tests {
     use crate::header::WarcHeader;
@@ -1082,10 +1722,13 @@ mod raw_tests {
 mod builder_tests {
     use crate::header::WarcHeader;
     use crate::{
-        BufferedBody, EmptyBody, RawRecordHeader, Record, RecordBuilder, RecordType, TruncatedType,
+        BufferedBody, EmptyBody, Error, MediaType, RawRecordHeader, Record, RecordBuilder,
+        RecordType, TruncatedType, UlidGenerator, UuidV5Generator,
     };
 
     use std::convert::TryFrom;
+    use std::net::IpAddr;
+    use url::Url;
 
     #[test]
     fn default() {
@@ -1345,6 +1988,162 @@ mod builder_tests {
         );
     }
 
+    #[test]
+    fn verify_build_record_id_uri() {
+        let builder = RecordBuilder::default();
+        let record = builder.clone().build().unwrap();
+        assert_eq!(record.record_id().unwrap().uri().scheme(), "urn");
+
+        match builder.clone().warc_id("urn:test:no-brackets").build() {
+            Err(Error::MalformedHeader(WarcHeader::RecordID, _)) => {}
+            other => panic!("{:?}", other),
+        }
+
+        for header in [
+            WarcHeader::RefersTo,
+            WarcHeader::ConcurrentTo,
+            WarcHeader::WarcInfoID,
+        ] {
+            let valid = builder.clone().header(header.clone(), "<urn:test:other>");
+            assert!(valid.build().is_ok());
+
+            let invalid = builder.clone().header(header.clone(), "other");
+            match invalid.build() {
+                Err(Error::MalformedHeader(h, _)) => assert_eq!(h, header),
+                other => panic!("{:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn verify_build_id_generator() {
+        let builder = RecordBuilder::default()
+            .id_generator(UuidV5Generator::default())
+            .header(WarcHeader::TargetURI, "http://example.com/");
+        let first = builder.clone().body(b"a".to_vec()).build().unwrap();
+        let second = builder.clone().body(b"a".to_vec()).build().unwrap();
+        let third = builder.clone().body(b"b".to_vec()).build().unwrap();
+        assert_eq!(first.warc_id(), second.warc_id());
+        assert_ne!(first.warc_id(), third.warc_id());
+        let (headers, _) = builder.clone().body(b"a".to_vec()).build_raw();
+        assert_eq!(
+            first.warc_id().as_bytes(),
+            headers.as_ref()[&WarcHeader::RecordID].as_slice()
+        );
+
+        let explicit = builder.warc_id("<urn:test:explicit>").build().unwrap();
+        assert_eq!(explicit.warc_id(), "<urn:test:explicit>");
+
+        let record = RecordBuilder::default()
+            .id_generator(UlidGenerator)
+            .build()
+            .unwrap();
+        assert!(record.warc_id().starts_with("<urn:ulid:"));
+    }
+
+    #[test]
+    fn verify_build_target_uri() {
+        let url = Url::parse("http://example.com/a?b=c").unwrap();
+        let record = RecordBuilder::default().target_uri(&url).build().unwrap();
+        assert_eq!(record.target_uri(), Some(url));
+        assert_eq!(record.raw_target_uri(), Some("http://example.com/a?b=c"));
+
+        let target_uri = |raw: &str| {
+            RecordBuilder::default()
+                .header(WarcHeader::TargetURI, raw)
+                .build()
+                .unwrap()
+                .target_uri()
+                .map(String::from)
+        };
+        assert_eq!(
+            target_uri("<http://example.com/>").as_deref(),
+            Some("http://example.com/")
+        );
+        assert_eq!(
+            target_uri(" http://bücher.example/straße?q=ü ").as_deref(),
+            Some("http://xn--bcher-kva.example/stra%C3%9Fe?q=%C3%BC")
+        );
+        assert_eq!(
+            target_uri("http://example.com/100%/%41%g").as_deref(),
+            Some("http://example.com/100%25/%41%25g")
+        );
+        assert_eq!(target_uri("not a uri"), None);
+        assert_eq!(RecordBuilder::default().build().unwrap().target_uri(), None);
+    }
+
+    #[test]
+    fn verify_build_content_type() {
+        let http = MediaType::new("application", "http").with_param("msgtype", "response");
+        let record = RecordBuilder::default()
+            .warc_type(RecordType::Response)
+            .content_type(&http)
+            .build()
+            .unwrap();
+        assert_eq!(record.content_type(), Some(http));
+        assert!(record.is_http_response());
+        assert!(!record.is_http_request());
+        assert!(!record.is_dns());
+
+        let with_type = |record_type, content_type: &str| {
+            RecordBuilder::default()
+                .warc_type(record_type)
+                .header(WarcHeader::ContentType, content_type)
+                .build()
+                .unwrap()
+        };
+        assert!(with_type(RecordType::Request, "application/http").is_http_request());
+        assert!(with_type(RecordType::Response, "application/http").is_http_response());
+        assert!(!with_type(RecordType::Resource, "application/http").is_http_response());
+        assert!(
+            with_type(RecordType::Resource, "Application/HTTP; msgtype=request").is_http_request()
+        );
+        assert!(with_type(RecordType::Response, "text/dns").is_dns());
+
+        let record = RecordBuilder::default()
+            .header(WarcHeader::IdentifiedPayloadType, "text/html")
+            .build()
+            .unwrap();
+        assert!(record.identified_payload_type().unwrap().is("text", "html"));
+
+        for header in [WarcHeader::ContentType, WarcHeader::IdentifiedPayloadType] {
+            match RecordBuilder::default()
+                .header(header.clone(), "html")
+                .build()
+            {
+                Err(Error::MalformedHeader(h, _)) => assert_eq!(h, header),
+                other => panic!("{:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn verify_build_ip_address() {
+        let address: IpAddr = "2001:db8::1".parse().unwrap();
+        let record = RecordBuilder::default()
+            .ip_address(address)
+            .build()
+            .unwrap();
+        assert_eq!(record.ip_address(), Some(address));
+        assert_eq!(record.header(WarcHeader::IPAddress).unwrap(), "2001:db8::1");
+
+        let record = RecordBuilder::default()
+            .header(WarcHeader::IPAddress, "192.0.2.1")
+            .build()
+            .unwrap();
+        assert_eq!(record.ip_address(), Some([192, 0, 2, 1].into()));
+
+        for bad in ["192.0.2.256", "2001:db8:::1", "example.com"] {
+            match RecordBuilder::default()
+                .header(WarcHeader::IPAddress, bad)
+                .build()
+            {
+                Err(Error::MalformedHeader(WarcHeader::IPAddress, _)) => {}
+                other => panic!("{}: {:?}", bad, other),
+            }
+        }
+    }
+
     #[test]
     fn verify_build_truncated_type() {
         const TRUNCATED_TYPE_0: &[u8] = b"length";
@@ -1424,3 +2223,89 @@ mod builder_tests {
         );
     }
 }
+
+#[cfg(all(test, feature = "with_serde"))]
+mod serde_tests {
+    use crate::{
+        BodyEncoding, BufferedBody, EmptyBody, RawRecordHeader, Record, RecordBuilder, RecordType,
+        TruncatedType, WarcHeader,
+    };
+
+    use chrono::prelude::*;
+
+    fn record() -> Record<BufferedBody> {
+        RecordBuilder::default()
+            .warc_id("<urn:test:serde>")
+            .warc_type(RecordType::Resource)
+            .date(Utc.with_ymd_and_hms(2020, 7, 8, 2, 52, 55).unwrap())
+            .truncated_type(TruncatedType::Length)
+            .header(WarcHeader::TargetURI, "http://example.com/")
+            .body(b"hello".to_vec())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let record = record();
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(
+            json,
+            "{\"version\":\"1.0\",\"headers\":{\"content-length\":\"5\",\
+             \"warc-date\":\"2020-07-08T02:52:55Z\",\"warc-record-id\":\"<urn:test:serde>\",\
+             \"warc-target-uri\":\"http://example.com/\",\"warc-truncated\":\"length\",\
+             \"warc-type\":\"resource\"},\"body\":\"aGVsbG8=\"}"
+        );
+        assert_eq!(
+            serde_json::from_str::<Record<BufferedBody>>(&json).unwrap(),
+            record
+        );
+    }
+
+    #[test]
+    fn record_omitted_body() {
+        let record = record();
+        let json = serde_json::to_value(record.with_body_encoding(BodyEncoding::Omitted)).unwrap();
+        assert!(json.get("body").is_none());
+        assert_eq!(json["headers"]["content-length"], "5");
+
+        let parsed: Record<BufferedBody> = serde_json::from_value(json.clone()).unwrap();
+        assert!(parsed.body().is_empty());
+        assert_eq!(parsed.warc_id(), "<urn:test:serde>");
+
+        let empty: Record<EmptyBody> = serde_json::from_value(json).unwrap();
+        assert_eq!(empty.warc_id(), record.warc_id());
+        assert_eq!(empty.date(), record.date());
+        assert_eq!(empty.truncated_type(), record.truncated_type());
+        assert_eq!(
+            empty.header(WarcHeader::TargetURI),
+            record.header(WarcHeader::TargetURI)
+        );
+        let json = serde_json::to_value(&empty).unwrap();
+        assert!(json["headers"].get("content-length").is_none());
+        assert_eq!(json["headers"]["warc-truncated"], "length");
+    }
+
+    #[test]
+    fn raw_header_round_trip() {
+        let (header, _) = record().into_raw_parts();
+        let json = serde_json::to_string(&header).unwrap();
+        assert_eq!(
+            serde_json::from_str::<RawRecordHeader>(&json).unwrap(),
+            header
+        );
+
+        assert!(serde_json::from_str::<Record<BufferedBody>>(
+            "{\"version\":\"1.0\",\"headers\":{\"warc-type\":\"resource\"}}"
+        )
+        .is_err());
+        assert_eq!(
+            serde_json::to_string(&RecordType::Unknown("custom".into())).unwrap(),
+            "\"custom\""
+        );
+        assert_eq!(
+            serde_json::from_str::<TruncatedType>("\"time\"").unwrap(),
+            TruncatedType::Time
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt;
+use std::str::FromStr;
+
+use url::Url;
+use uuid::Uuid;
+
+use crate::header::WarcHeader;
+use crate::{BufferedBody, Error, Record};
+
+/// A record identifier, as used by the `WARC-Record-ID` header and the headers which refer to
+/// other records.
+///
+/// The standard requires identifiers to be URIs enclosed in angle brackets, such as
+/// `<urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6>`. Use the `Display` trait to generate the
+/// bracketed representation.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RecordId(Url);
+
+impl RecordId {
+    /// Create an identifier from a URI.
+    pub fn new(uri: Url) -> Self {
+        RecordId(uri)
+    }
+
+    /// Generate a new, globally unique identifier in the `urn:uuid` scheme.
+    pub fn generate() -> Self {
+        RecordId(Url::parse(&Uuid::new_v4().to_urn().to_string()).expect("a UUID URN is a URI"))
+    }
+
+    /// Parse an identifier from its bracketed representation.
+    pub fn parse(id: &str) -> Result<Self, Error> {
+        RecordId::parse_header(WarcHeader::RecordID, id)
+    }
+
+    /// Parse the value of a header holding a record identifier.
+    pub(crate) fn parse_header(header: WarcHeader, id: &str) -> Result<Self, Error> {
+        id.trim()
+            .strip_prefix('<')
+            .and_then(|id| id.strip_suffix('>'))
+            .and_then(|uri| Url::parse(uri).ok())
+            .map(RecordId)
+            .ok_or_else(|| {
+                Error::MalformedHeader(header, "not a URI in angle brackets".to_string())
+            })
+    }
+
+    /// Return the URI of this identifier.
+    pub fn uri(&self) -> &Url {
+        &self.0
+    }
+
+    /// Return the URI of this identifier.
+    pub fn into_uri(self) -> Url {
+        self.0
+    }
+}
+
+impl fmt::Display for RecordId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.0)
+    }
+}
+
+impl FromStr for RecordId {
+    type Err = Error;
+
+    fn from_str(id: &str) -> Result<Self, Error> {
+        RecordId::parse(id)
+    }
+}
+
+impl From<Url> for RecordId {
+    fn from(uri: Url) -> Self {
+        RecordId(uri)
+    }
+}
+
+impl From<RecordId> for String {
+    fn from(id: RecordId) -> Self {
+        id.to_string()
+    }
+}
+
+/// A strategy for generating the IDs of new records.
+///
+/// Generators are given the record the ID is for, with the headers and body set so far, so that
+/// an ID can be derived from them.
+pub trait RecordIdGenerator {
+    /// Generate an ID for a record.
+    fn generate(&self, record: &Record<BufferedBody>) -> RecordId;
+}
+
+/// Generates random `urn:uuid` IDs from version 4 UUIDs.
+///
+/// This is the generator used when no other is given.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UuidV4Generator;
+
+impl RecordIdGenerator for UuidV4Generator {
+    fn generate(&self, _: &Record<BufferedBody>) -> RecordId {
+        RecordId::generate()
+    }
+}
+
+/// Generates deterministic `urn:uuid` IDs from version 5 UUIDs.
+///
+/// A record is named by its type, `WARC-Target-URI`, date, and its `WARC-Payload-Digest`,
+/// `WARC-Block-Digest` or else its body, in that order of preference. Writing the same records
+/// again produces the same IDs.
+#[derive(Clone, Copy, Debug)]
+pub struct UuidV5Generator {
+    namespace: Uuid,
+}
+
+impl UuidV5Generator {
+    /// Create a generator whose IDs are distinct from those generated in any other namespace.
+    pub fn new(namespace: &str) -> Self {
+        UuidV5Generator {
+            namespace: Uuid::new_v5(&Uuid::NAMESPACE_URL, namespace.as_bytes()),
+        }
+    }
+}
+
+impl Default for UuidV5Generator {
+    fn default() -> Self {
+        UuidV5Generator {
+            namespace: Uuid::NAMESPACE_URL,
+        }
+    }
+}
+
+impl RecordIdGenerator for UuidV5Generator {
+    fn generate(&self, record: &Record<BufferedBody>) -> RecordId {
+        let mut name = format!(
+            "{} {} {} ",
+            record.warc_type(),
+            record.header(WarcHeader::TargetURI).unwrap_or_default(),
+            record.date().to_rfc3339()
+        )
+        .into_bytes();
+        match record
+            .header(WarcHeader::PayloadDigest)
+            .or_else(|| record.header(WarcHeader::BlockDigest))
+        {
+            Some(digest) => name.extend_from_slice(digest.as_bytes()),
+            None => name.extend_from_slice(record.body()),
+        }
+
+        let uuid = Uuid::new_v5(&self.namespace, &name);
+        RecordId(Url::parse(&uuid.to_urn().to_string()).expect("a UUID URN is a URI"))
+    }
+}
+
+/// Generates `urn:ulid` IDs, which sort by the date of the record they identify.
+///
+/// The first 48 bits of a ULID are the record's `WARC-Date` in milliseconds since the Unix epoch,
+/// and the remaining 80 bits are random.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UlidGenerator;
+
+impl RecordIdGenerator for UlidGenerator {
+    fn generate(&self, record: &Record<BufferedBody>) -> RecordId {
+        const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+        let millis = record.date().timestamp_millis().clamp(0, (1 << 48) - 1) as u128;
+        // NB: the version and variant bits of a v4 UUID are in bytes 6 and 8
+        let random = Uuid::new_v4();
+        let random = random.as_bytes();
+        let random = random[..6]
+            .iter()
+            .chain(random[10..14].iter())
+            .fold(0u128, |acc, &b| (acc << 8) | b as u128);
+
+        let value = (millis << 80) | random;
+        let ulid = (0..26)
+            .map(|i| ALPHABET[((value >> (125 - 5 * i)) & 31) as usize] as char)
+            .collect::<String>();
+
+        RecordId(Url::parse(&format!("urn:ulid:{}", ulid)).expect("a ULID URN is a URI"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RecordId, RecordIdGenerator, UlidGenerator, UuidV5Generator};
+    use crate::{Error, RecordBuilder, WarcHeader};
+
+    use chrono::prelude::*;
+
+    #[test]
+    fn parse_and_format() {
+        let raw = "<urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6>";
+        let id = RecordId::parse(raw).unwrap();
+        assert_eq!(id.uri().scheme(), "urn");
+        assert_eq!(id.to_string(), raw);
+        assert_eq!(raw.parse::<RecordId>().unwrap(), id);
+
+        for bad in [
+            "urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6",
+            "<f81d4fae-7dec-11d0-a765-00a0c91e6bf6>",
+            "<>",
+            "<urn:x",
+        ] {
+            match RecordId::parse(bad) {
+                Err(Error::MalformedHeader(WarcHeader::RecordID, _)) => {}
+                other => panic!("{}: {:?}", bad, other),
+            }
+        }
+    }
+
+    #[test]
+    fn generate() {
+        let id = RecordId::generate();
+        assert_ne!(id, RecordId::generate());
+        assert!(id.to_string().starts_with("<urn:uuid:"));
+        assert_eq!(RecordId::parse(&id.to_string()).unwrap(), id);
+    }
+
+    #[test]
+    fn generators() {
+        let date = Utc.with_ymd_and_hms(2020, 7, 8, 2, 52, 55).unwrap();
+        let record = |uri: &str| {
+            RecordBuilder::default()
+                .date(date)
+                .header(WarcHeader::TargetURI, uri)
+                .body(b"hello".to_vec())
+                .build()
+                .unwrap()
+        };
+
+        let v5 = UuidV5Generator::default();
+        let id = v5.generate(&record("http://example.com/"));
+        assert_eq!(id, v5.generate(&record("http://example.com/")));
+        assert_ne!(id, v5.generate(&record("http://example.com/a")));
+        assert_ne!(
+            id,
+            UuidV5Generator::new("test").generate(&record("http://example.com/"))
+        );
+        assert!(id.to_string().starts_with("<urn:uuid:"));
+
+        let ulid = UlidGenerator.generate(&record("http://example.com/"));
+        assert!(ulid.to_string().starts_with("<urn:ulid:01ECP5E8TR"));
+        assert_eq!(ulid.to_string().len(), 37);
+        let earlier = RecordBuilder::default()
+            .date(date - chrono::Duration::milliseconds(1))
+            .build()
+            .unwrap();
+        assert!(UlidGenerator.generate(&earlier).to_string() < ulid.to_string());
+    }
+}
@@ -1,7 +1,14 @@
 #![allow(missing_docs)]
 
 use std::fmt::Display;
+
+#[cfg(feature = "with_serde")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "with_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "with_serde", serde(into = "String"))]
+#[cfg_attr(feature = "with_serde", serde(from = "String"))]
 pub enum RecordType {
     WarcInfo,
     Response,
@@ -14,6 +21,12 @@ pub enum RecordType {
     Unknown(String),
 }
 
+impl From<RecordType> for String {
+    fn from(value: RecordType) -> Self {
+        value.to_string()
+    }
+}
+
 impl Display for RecordType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let stringified = match *self {
@@ -0,0 +1,85 @@
+//! Sort-friendly URI Reordering Transform (SURT) keys, as used by CDX indexes.
+//!
+//! A SURT key reverses the labels of a URL's host, so that all captures of a domain and its
+//! subdomains sort next to each other:
+//!
+//! ```
+//! use url::Url;
+//!
+//! let url = Url::parse("https://www.Example.com/Path?b=2&a=1#top").unwrap();
+//! assert_eq!(warc::surt::surt(&url), "com,example)/path?a=1&b=2");
+//! ```
+
+use url::{Host, Url};
+
+/// Generate the SURT key of a URL.
+///
+/// The URL is canonicalized the way most wayback tools do before indexing it: the scheme, user
+/// information and fragment are dropped, a leading `www` label is removed, query parameters are
+/// sorted, and the whole key is lowercased. Non-default ports are kept.
+pub fn surt(url: &Url) -> String {
+    let mut key = match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            let mut labels = domain.split('.').collect::<Vec<_>>();
+            if labels.len() > 2 && is_www(labels[0]) {
+                labels.remove(0);
+            }
+            labels.reverse();
+            labels.join(",")
+        }
+        Some(host) => host.to_string(),
+        None => String::new(),
+    };
+    if let Some(port) = url.port() {
+        key.push(':');
+        key.push_str(&port.to_string());
+    }
+    key.push(')');
+    key.push_str(&url.path().to_lowercase());
+
+    if let Some(query) = url.query() {
+        let mut params = query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        params.sort();
+        if !params.is_empty() {
+            key.push('?');
+            key.push_str(&params.join("&"));
+        }
+    }
+
+    key
+}
+
+fn is_www(label: &str) -> bool {
+    label
+        .strip_prefix("www")
+        .map(|rest| rest.chars().all(|c| c.is_ascii_digit()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::surt;
+    use url::Url;
+
+    fn key(url: &str) -> String {
+        surt(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn canonicalize() {
+        assert_eq!(key("http://example.com"), "com,example)/");
+        assert_eq!(key("http://www2.sub.Example.com/A"), "com,example,sub)/a");
+        assert_eq!(key("http://www.com/"), "com,www)/");
+        assert_eq!(key("https://user:pw@example.com:443/#x"), "com,example)/");
+        assert_eq!(
+            key("http://example.com:8080/a?z&b=1&"),
+            "com,example:8080)/a?b=1&z"
+        );
+        assert_eq!(key("http://127.0.0.1/x"), "127.0.0.1)/x");
+    }
+}
@@ -0,0 +1,183 @@
+//! Conversions between dates and wayback timestamps.
+//!
+//! A wayback timestamp is a date written as 14 digits, `YYYYMMDDhhmmss`, in UTC. A timestamp may
+//! be cut short after any of its fields to name a whole period: `2020` is the year 2020, and
+//! `202002` is February 2020.
+//!
+//! ```
+//! use warc::timestamp;
+//!
+//! let start = timestamp::parse("202002").unwrap();
+//! let end = timestamp::parse_end("202002").unwrap();
+//! assert_eq!(timestamp::format(&start), "20200201000000");
+//! assert_eq!(timestamp::format(&end), "20200229235959");
+//! ```
+
+use chrono::prelude::*;
+use chrono::Duration;
+
+use crate::{BufferedBody, Error, Record};
+
+const START: &str = "00000101000000";
+
+/// Format a date as a 14-digit timestamp.
+pub fn format(date: &DateTime<Utc>) -> String {
+    date.format("%Y%m%d%H%M%S").to_string()
+}
+
+fn malformed(timestamp: &str) -> Error {
+    Error::ParseTimestamp(timestamp.to_string())
+}
+
+/// Parse a timestamp, or a prefix of one, into the first second of the period it names.
+pub fn parse(timestamp: &str) -> Result<DateTime<Utc>, Error> {
+    // a year, and any number of the two-digit fields which follow it
+    if ![4, 6, 8, 10, 12, 14].contains(&timestamp.len())
+        || !timestamp.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(malformed(timestamp));
+    }
+    let full = format!("{}{}", timestamp, &START[timestamp.len()..]);
+
+    NaiveDateTime::parse_from_str(&full, "%Y%m%d%H%M%S")
+        .map(|date| Utc.from_utc_datetime(&date))
+        .map_err(|_| malformed(timestamp))
+}
+
+/// Parse a timestamp, or a prefix of one, into the last second of the period it names.
+pub fn parse_end(timestamp: &str) -> Result<DateTime<Utc>, Error> {
+    let start = parse(timestamp)?;
+    let next = match timestamp.len() {
+        4 => start.with_year(start.year() + 1),
+        6 if start.month() == 12 => start
+            .with_month(1)
+            .and_then(|date| date.with_year(start.year() + 1)),
+        6 => start.with_month(start.month() + 1),
+        8 => Some(start + Duration::days(1)),
+        10 => Some(start + Duration::hours(1)),
+        12 => Some(start + Duration::minutes(1)),
+        _ => Some(start + Duration::seconds(1)),
+    }
+    .ok_or_else(|| malformed(timestamp))?;
+
+    Ok(next - Duration::seconds(1))
+}
+
+/// An inclusive range of capture dates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DateRange {
+    /// The earliest date in the range.
+    pub start: DateTime<Utc>,
+    /// The latest date in the range.
+    pub end: DateTime<Utc>,
+}
+
+impl DateRange {
+    /// Create a range between two dates, inclusive.
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        DateRange { start, end }
+    }
+
+    /// Create a range covering the period named by a timestamp, or a prefix of one.
+    pub fn from_timestamp(timestamp: &str) -> Result<Self, Error> {
+        Ok(DateRange::new(parse(timestamp)?, parse_end(timestamp)?))
+    }
+
+    /// Create a range between the periods named by two timestamps, inclusive.
+    pub fn from_timestamps(start: &str, end: &str) -> Result<Self, Error> {
+        Ok(DateRange::new(parse(start)?, parse_end(end)?))
+    }
+
+    /// Return `true` if the date falls within this range.
+    pub fn contains(&self, date: &DateTime<Utc>) -> bool {
+        &self.start <= date && date <= &self.end
+    }
+}
+
+/// Return the record captured nearest to a date, reading all records from an iterator.
+///
+/// Of two records equally distant, the earlier one is returned. The first error read is returned
+/// instead.
+pub fn nearest<I>(records: I, date: &DateTime<Utc>) -> Result<Option<Record<BufferedBody>>, Error>
+where
+    I: IntoIterator<Item = Result<Record<BufferedBody>, Error>>,
+{
+    let mut nearest: Option<(Record<BufferedBody>, i64)> = None;
+    for record in records {
+        let record = record?;
+        let distance = (*record.date() - *date).num_seconds();
+        let closer = match nearest {
+            Some((_, best)) => (distance.abs(), distance) < (best.abs(), best),
+            None => true,
+        };
+        if closer {
+            nearest = Some((record, distance));
+        }
+    }
+
+    Ok(nearest.map(|(record, _)| record))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format, nearest, parse, parse_end, DateRange};
+    use crate::{Error, RecordBuilder};
+
+    use chrono::prelude::*;
+
+    #[test]
+    fn partial_timestamps() {
+        let date = |y, mo, d, h, mi, s| Utc.with_ymd_and_hms(y, mo, d, h, mi, s).unwrap();
+        assert_eq!(parse("2020").unwrap(), date(2020, 1, 1, 0, 0, 0));
+        assert_eq!(parse_end("2020").unwrap(), date(2020, 12, 31, 23, 59, 59));
+        assert_eq!(parse_end("202012").unwrap(), date(2020, 12, 31, 23, 59, 59));
+        assert_eq!(
+            parse_end("2021022812").unwrap(),
+            date(2021, 2, 28, 12, 59, 59)
+        );
+        assert_eq!(
+            parse("20200708025255").unwrap(),
+            parse_end("20200708025255").unwrap()
+        );
+        assert_eq!(format(&date(2020, 7, 8, 2, 52, 55)), "20200708025255");
+
+        for bad in [
+            "",
+            "202",
+            "20201",
+            "2020133",
+            "202013",
+            "20200230",
+            "2020070802525x",
+        ] {
+            assert!(
+                matches!(parse(bad), Err(Error::ParseTimestamp(ref t)) if t == bad),
+                "{}",
+                bad
+            );
+        }
+
+        let range = DateRange::from_timestamps("2020", "202103").unwrap();
+        assert!(range.contains(&date(2021, 3, 31, 23, 59, 59)));
+        assert!(!range.contains(&date(2021, 4, 1, 0, 0, 0)));
+        assert!(!range.contains(&date(2019, 12, 31, 23, 59, 59)));
+    }
+
+    #[test]
+    fn nearest_record() {
+        let record = |timestamp| RecordBuilder::default().timestamp(timestamp).build();
+        let records = || vec![record("2020"), record("2022"), record("2024")];
+
+        let found = nearest(records(), &parse("202006").unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.timestamp(), "20200101000000");
+        let found = nearest(records(), &parse("2023").unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.timestamp(), "20220101000000");
+
+        assert!(nearest(vec![record("20")], &Utc::now()).is_err());
+        assert!(nearest(Vec::new(), &Utc::now()).unwrap().is_none());
+    }
+}
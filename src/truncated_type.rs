@@ -1,7 +1,14 @@
 #![allow(missing_docs)]
 
 use std::fmt::Display;
+
+#[cfg(feature = "with_serde")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "with_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "with_serde", serde(into = "String"))]
+#[cfg_attr(feature = "with_serde", serde(from = "String"))]
 pub enum TruncatedType {
     Length,
     Time,
@@ -10,6 +17,12 @@ pub enum TruncatedType {
     Unknown(String),
 }
 
+impl From<TruncatedType> for String {
+    fn from(value: TruncatedType) -> Self {
+        value.to_string()
+    }
+}
+
 impl Display for TruncatedType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let stringified = match *self {
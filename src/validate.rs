@@ -0,0 +1,928 @@
+//! Conformance checks against the WARC 1.0 and 1.1 specifications.
+//!
+//! Parsing a record only requires its headers to be well-formed, and the four headers every
+//! record needs. A [`Validator`] checks each record read against the rest of the specification:
+//! which headers each record type must or must not carry, the syntax of their values, the
+//! consistency of segmented records, and, with the `digest` feature, the block and payload
+//! digests.
+//!
+//! ```
+//! use warc::validate;
+//!
+//! let data = b"WARC/1.1\r\n\
+//!     WARC-Type: response\r\n\
+//!     WARC-Record-ID: urn:uuid:00000000-0000-0000-0000-000000000000\r\n\
+//!     WARC-Date: 2020-07-08T02:52:55Z\r\n\
+//!     Content-Length: 0\r\n\
+//!     \r\n\
+//!     \r\n\
+//!     \r\n";
+//!
+//! let report = validate::validate_slice(data);
+//! assert!(!report.is_valid());
+//! for record in report.records() {
+//!     for issue in record.issues() {
+//!         println!("{}: {}", record.offset(), issue);
+//!     }
+//! }
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::net::IpAddr;
+use std::str;
+
+use chrono::DateTime;
+
+use crate::header::WarcHeader;
+use crate::record_type::RecordType;
+use crate::truncated_type::TruncatedType;
+use crate::{EmptyBody, Error, MediaType, Record, RecordId, RecordRef, WarcSlice};
+
+/// How severe a conformance issue is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The record breaks a rule the specification says "shall" or "must" hold.
+    Error,
+    /// The record breaks a recommendation, or uses a value other tools may not understand.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// A single conformance issue found in a record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Issue {
+    severity: Severity,
+    header: Option<WarcHeader>,
+    message: String,
+}
+
+impl Issue {
+    fn new<S: Into<String>>(severity: Severity, header: Option<WarcHeader>, message: S) -> Self {
+        Issue {
+            severity,
+            header,
+            message: message.into(),
+        }
+    }
+
+    /// Return how severe this issue is.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Return the header this issue is about, if it is about a single header.
+    pub fn header(&self) -> Option<&WarcHeader> {
+        self.header.as_ref()
+    }
+
+    /// Return a description of this issue.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.header {
+            Some(ref header) => write!(f, "{}: {}: {}", self.severity, header, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// The issues found in a single record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordReport {
+    offset: u64,
+    record_id: Option<String>,
+    record_type: Option<RecordType>,
+    issues: Vec<Issue>,
+}
+
+impl RecordReport {
+    /// Return the offset of the record within the data it was read from.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Return the `WARC-Record-ID` of the record, if it could be read.
+    pub fn record_id(&self) -> Option<&str> {
+        self.record_id.as_deref()
+    }
+
+    /// Return the `WARC-Type` of the record, if it could be read.
+    pub fn record_type(&self) -> Option<&RecordType> {
+        self.record_type.as_ref()
+    }
+
+    /// Return the issues found in the record.
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+
+    /// Return `true` if no errors were found in the record. There may still be warnings.
+    pub fn is_valid(&self) -> bool {
+        self.issues.iter().all(|i| i.severity != Severity::Error)
+    }
+
+    fn push<S: Into<String>>(&mut self, severity: Severity, header: Option<WarcHeader>, msg: S) {
+        self.issues.push(Issue::new(severity, header, msg));
+    }
+}
+
+/// A conformance report, with the issues found in every record checked.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    records: Vec<RecordReport>,
+}
+
+impl Report {
+    /// Return the reports of all records checked, in the order they were read.
+    pub fn records(&self) -> &[RecordReport] {
+        &self.records
+    }
+
+    /// Return the number of errors found.
+    pub fn errors(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    /// Return the number of warnings found.
+    pub fn warnings(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    /// Return `true` if no errors were found. There may still be warnings.
+    pub fn is_valid(&self) -> bool {
+        self.errors() == 0
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.records
+            .iter()
+            .flat_map(|r| r.issues.iter())
+            .filter(|i| i.severity == severity)
+            .count()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for record in self.records.iter() {
+            for issue in record.issues.iter() {
+                write!(f, "offset {}", record.offset)?;
+                if let Some(ref id) = record.record_id {
+                    write!(f, " ({})", id)?;
+                }
+                writeln!(f, ": {}", issue)?;
+            }
+        }
+        write!(
+            f,
+            "{} records, {} errors, {} warnings",
+            self.records.len(),
+            self.errors(),
+            self.warnings()
+        )
+    }
+}
+
+struct SegmentState {
+    report: usize,
+    next: u64,
+    length: u64,
+}
+
+/// Checks records for conformance, keeping the state needed to check the records which relate
+/// to each other.
+///
+/// Records should be checked in the order they appear in a file. Segments are matched to the
+/// record they continue by `WARC-Segment-Origin-ID`, and must follow it in order.
+#[derive(Default)]
+pub struct Validator {
+    report: Report,
+    record_ids: HashSet<String>,
+    segments: HashMap<String, SegmentState>,
+}
+
+impl Validator {
+    /// Create a new validator.
+    pub fn new() -> Self {
+        Validator::default()
+    }
+
+    /// Check a record, read from the given offset.
+    pub fn check(&mut self, offset: u64, record: &RecordRef<'_>) -> &RecordReport {
+        let mut report = RecordReport {
+            offset,
+            record_id: record
+                .header(&WarcHeader::RecordID)
+                .and_then(utf8)
+                .map(Into::into),
+            record_type: record
+                .header(&WarcHeader::WarcType)
+                .and_then(utf8)
+                .map(RecordType::from),
+            issues: Vec::new(),
+        };
+
+        check_record(record, &mut report);
+        #[cfg(feature = "digest")]
+        check_digests(record, &mut report);
+        self.check_relations(record, &mut report);
+
+        self.report.records.push(report);
+        self.report.records.last().unwrap()
+    }
+
+    /// Record that the data at the given offset could not be parsed as a record.
+    pub fn check_error(&mut self, offset: u64, error: &Error) -> &RecordReport {
+        let mut report = RecordReport {
+            offset,
+            record_id: None,
+            record_type: None,
+            issues: Vec::new(),
+        };
+        report.push(
+            Severity::Error,
+            None,
+            format!("record could not be read: {}", error),
+        );
+
+        self.report.records.push(report);
+        self.report.records.last().unwrap()
+    }
+
+    /// Finish checking records, and return the report of all records checked.
+    pub fn finish(mut self) -> Report {
+        for (_, state) in self.segments.drain() {
+            self.report.records[state.report].push(
+                Severity::Warning,
+                Some(WarcHeader::SegmentNumber),
+                "the last segment of this record was not found",
+            );
+        }
+
+        self.report
+    }
+
+    fn check_relations(&mut self, record: &RecordRef<'_>, report: &mut RecordReport) {
+        if let Some(ref id) = report.record_id {
+            if !self.record_ids.insert(id.clone()) {
+                report.push(
+                    Severity::Error,
+                    Some(WarcHeader::RecordID),
+                    "another record has the same ID",
+                );
+            }
+        }
+
+        let number = match record
+            .header(&WarcHeader::SegmentNumber)
+            .and_then(utf8)
+            .and_then(|n| n.parse::<u64>().ok())
+        {
+            Some(number) => number,
+            None => return,
+        };
+        let length = record.body().len() as u64;
+
+        if report.record_type != Some(RecordType::Continuation) {
+            if let (1, Some(ref id)) = (number, &report.record_id) {
+                let state = SegmentState {
+                    report: self.report.records.len(),
+                    next: 2,
+                    length,
+                };
+                self.segments.insert(id.clone(), state);
+            }
+            return;
+        }
+
+        let origin = match record.header(&WarcHeader::SegmentOriginID).and_then(utf8) {
+            Some(origin) => origin,
+            None => return,
+        };
+        let state = match self.segments.get_mut(origin) {
+            Some(state) => state,
+            None => {
+                report.push(
+                    Severity::Warning,
+                    Some(WarcHeader::SegmentOriginID),
+                    "the record this segment continues was not found before it",
+                );
+                return;
+            }
+        };
+
+        if number != state.next {
+            report.push(
+                Severity::Error,
+                Some(WarcHeader::SegmentNumber),
+                format!("expected segment {}", state.next),
+            );
+        }
+        state.next = number + 1;
+        state.length += length;
+
+        let total = record
+            .header(&WarcHeader::SegmentTotalLength)
+            .and_then(utf8)
+            .and_then(|n| n.parse::<u64>().ok());
+        if let Some(total) = total {
+            if total != state.length {
+                report.push(
+                    Severity::Error,
+                    Some(WarcHeader::SegmentTotalLength),
+                    format!("the segments hold {} bytes", state.length),
+                );
+            }
+            self.segments.remove(origin);
+        }
+    }
+}
+
+/// Check all records in a byte slice holding an uncompressed WARC file.
+///
+/// Checking stops at the first record which cannot be parsed.
+pub fn validate_slice(data: &[u8]) -> Report {
+    let mut validator = Validator::new();
+    let mut offset = 0;
+    for record in WarcSlice::new(data) {
+        match record {
+            Ok(record) => {
+                validator.check(record.location().offset, &record);
+                offset = record.location().offset + record.location().length;
+            }
+            Err(e) => {
+                validator.check_error(offset, &e);
+            }
+        }
+    }
+
+    validator.finish()
+}
+
+fn utf8(value: &[u8]) -> Option<&str> {
+    str::from_utf8(value).ok()
+}
+
+/// Return `true` if a value is a URI in angle brackets, as record IDs are written.
+fn is_bracketed_uri(value: &str) -> bool {
+    RecordId::parse(value).is_ok()
+}
+
+/// Return `true` if a value is a URI as written, without the escaping `Record::target_uri` does.
+fn is_strict_uri(value: &str) -> bool {
+    let value = value.trim_start_matches('<').trim_end_matches('>');
+    let bytes = value.as_bytes();
+    value.is_ascii()
+        && !bytes
+            .iter()
+            .any(|b| b.is_ascii_whitespace() || b.is_ascii_control())
+        && bytes.iter().enumerate().all(|(i, &b)| {
+            b != b'%'
+                || (bytes.len() > i + 2
+                    && bytes[i + 1].is_ascii_hexdigit()
+                    && bytes[i + 2].is_ascii_hexdigit())
+        })
+}
+
+fn check_record(record: &RecordRef<'_>, report: &mut RecordReport) {
+    use RecordType::*;
+    use Severity::{Error, Warning};
+
+    if record.version() != "1.0" && record.version() != "1.1" {
+        report.push(
+            Warning,
+            None,
+            format!("unknown WARC version {}", record.version()),
+        );
+    }
+
+    let mut seen = HashSet::new();
+    for (token, value) in record.headers() {
+        let header = WarcHeader::from(*token);
+        if let WarcHeader::Unknown(_) = header {
+            continue;
+        }
+        if header != WarcHeader::ConcurrentTo && !seen.insert(header.clone()) {
+            report.push(Error, Some(header.clone()), "appears more than once");
+        }
+        if utf8(value).is_none() {
+            report.push(Error, Some(header), "not a UTF-8 string");
+        }
+    }
+
+    let header = |h: WarcHeader| record.header(&h).and_then(utf8).map(str::trim);
+    let record_type = match report.record_type.clone() {
+        Some(record_type) => record_type,
+        None => {
+            report.push(Error, Some(WarcHeader::WarcType), "missing");
+            return;
+        }
+    };
+    if let Unknown(ref name) = record_type {
+        report.push(
+            Warning,
+            Some(WarcHeader::WarcType),
+            format!("unknown record type {}", name),
+        );
+    }
+
+    match header(WarcHeader::RecordID) {
+        None => report.push(Error, Some(WarcHeader::RecordID), "missing"),
+        Some(id) if !is_bracketed_uri(id) => report.push(
+            Error,
+            Some(WarcHeader::RecordID),
+            "not a URI in angle brackets",
+        ),
+        Some(_) => {}
+    }
+
+    match header(WarcHeader::Date) {
+        None => report.push(Error, Some(WarcHeader::Date), "missing"),
+        Some(date) if DateTime::parse_from_rfc3339(date).is_err() => {
+            report.push(Error, Some(WarcHeader::Date), "not an ISO 8601 datestamp")
+        }
+        Some(_) => {}
+    }
+
+    let body = record.body();
+    match header(WarcHeader::ContentType) {
+        None if !body.is_empty() && record_type != Continuation => report.push(
+            Warning,
+            Some(WarcHeader::ContentType),
+            "missing for a record with a block",
+        ),
+        Some(_) if record_type == Continuation => report.push(
+            Warning,
+            Some(WarcHeader::ContentType),
+            "should not be used in continuation records",
+        ),
+        Some(value) if MediaType::parse(value).is_err() => {
+            report.push(Error, Some(WarcHeader::ContentType), "not a media type")
+        }
+        Some(value)
+            if record_type == WarcInfo
+                && !MediaType::parse(value).is_ok_and(|t| t.is("application", "warc-fields")) =>
+        {
+            report.push(
+                Warning,
+                Some(WarcHeader::ContentType),
+                "warcinfo records should contain application/warc-fields",
+            )
+        }
+        _ => {}
+    }
+
+    let target_uri = header(WarcHeader::TargetURI);
+    match (&record_type, target_uri) {
+        (Response | Resource | Request | Revisit | Conversion | Continuation, None) => report.push(
+            Error,
+            Some(WarcHeader::TargetURI),
+            format!("missing for {} records", record_type),
+        ),
+        (WarcInfo, Some(_)) => report.push(
+            Error,
+            Some(WarcHeader::TargetURI),
+            "not allowed in warcinfo records",
+        ),
+        (_, Some(uri)) if Record::<EmptyBody>::parse_target_uri(uri).is_none() => {
+            report.push(Error, Some(WarcHeader::TargetURI), "not a URI")
+        }
+        (_, Some(uri)) if !is_strict_uri(uri) => report.push(
+            Warning,
+            Some(WarcHeader::TargetURI),
+            "only a URI once non-ASCII characters or `%` signs are escaped",
+        ),
+        _ => {}
+    }
+
+    let forbidden: &[(WarcHeader, &[RecordType])] = &[
+        (
+            WarcHeader::ConcurrentTo,
+            &[WarcInfo, Conversion, Continuation],
+        ),
+        (
+            WarcHeader::RefersTo,
+            &[WarcInfo, Response, Resource, Request, Continuation],
+        ),
+        (WarcHeader::WarcInfoID, &[WarcInfo]),
+    ];
+    for (h, types) in forbidden {
+        if record.header(h).is_some() && types.contains(&record_type) {
+            report.push(
+                Error,
+                Some(h.clone()),
+                format!("not allowed in {} records", record_type),
+            );
+        }
+    }
+    if record.header(&WarcHeader::Filename).is_some() && record_type != WarcInfo {
+        report.push(
+            Error,
+            Some(WarcHeader::Filename),
+            "only allowed in warcinfo records",
+        );
+    }
+
+    for (token, value) in record.headers() {
+        let h = WarcHeader::from(*token);
+        let is_id = matches!(
+            h,
+            WarcHeader::ConcurrentTo | WarcHeader::RefersTo | WarcHeader::WarcInfoID
+        );
+        if is_id && !utf8(value).map(str::trim).is_some_and(is_bracketed_uri) {
+            report.push(Error, Some(h), "not a URI in angle brackets");
+        }
+    }
+
+    if record_type == Revisit && header(WarcHeader::Profile).is_none() {
+        report.push(
+            Error,
+            Some(WarcHeader::Profile),
+            "missing for revisit records",
+        );
+    }
+
+    if let Some(value) = header(WarcHeader::IdentifiedPayloadType) {
+        if MediaType::parse(value).is_err() {
+            report.push(
+                Warning,
+                Some(WarcHeader::IdentifiedPayloadType),
+                "not a media type",
+            );
+        }
+    }
+
+    if let Some(value) = header(WarcHeader::IPAddress) {
+        if value.parse::<IpAddr>().is_err() {
+            report.push(
+                Error,
+                Some(WarcHeader::IPAddress),
+                "not an IPv4 or IPv6 address",
+            );
+        }
+    }
+
+    if let Some(value) = header(WarcHeader::Truncated) {
+        if let TruncatedType::Unknown(reason) = TruncatedType::from(value) {
+            report.push(
+                Warning,
+                Some(WarcHeader::Truncated),
+                format!("unknown reason {}", reason),
+            );
+        }
+    }
+
+    for h in [WarcHeader::BlockDigest, WarcHeader::PayloadDigest] {
+        if let Some(value) = header(h.clone()) {
+            match value.split_once(':') {
+                Some((algorithm, digest)) if !algorithm.is_empty() && !digest.is_empty() => {}
+                _ => report.push(Error, Some(h), "not a labelled digest"),
+            }
+        }
+    }
+
+    check_segment_headers(&record_type, &header, report);
+}
+
+fn check_segment_headers<'a, F>(record_type: &RecordType, header: &F, report: &mut RecordReport)
+where
+    F: Fn(WarcHeader) -> Option<&'a str>,
+{
+    use Severity::Error;
+
+    let number = header(WarcHeader::SegmentNumber);
+    let parsed = number.and_then(|n| n.parse::<u64>().ok());
+    if number.is_some() && !matches!(parsed, Some(n) if n > 0) {
+        report.push(
+            Error,
+            Some(WarcHeader::SegmentNumber),
+            "not a positive integer",
+        );
+    }
+    if let Some(total) = header(WarcHeader::SegmentTotalLength) {
+        if total.parse::<u64>().is_err() {
+            report.push(
+                Error,
+                Some(WarcHeader::SegmentTotalLength),
+                "not an integer",
+            );
+        }
+    }
+
+    if *record_type == RecordType::Continuation {
+        match header(WarcHeader::SegmentOriginID) {
+            None => report.push(
+                Error,
+                Some(WarcHeader::SegmentOriginID),
+                "missing for continuation records",
+            ),
+            Some(id) if !is_bracketed_uri(id) => report.push(
+                Error,
+                Some(WarcHeader::SegmentOriginID),
+                "not a URI in angle brackets",
+            ),
+            Some(_) => {}
+        }
+        match (number, parsed) {
+            (None, _) => report.push(
+                Error,
+                Some(WarcHeader::SegmentNumber),
+                "missing for continuation records",
+            ),
+            (_, Some(1)) => report.push(
+                Error,
+                Some(WarcHeader::SegmentNumber),
+                "continuation records start at segment 2",
+            ),
+            _ => {}
+        }
+        return;
+    }
+
+    if parsed.is_some_and(|n| n > 1) {
+        report.push(
+            Error,
+            Some(WarcHeader::SegmentNumber),
+            "only the first segment may be a record other than a continuation",
+        );
+    }
+    for h in [WarcHeader::SegmentOriginID, WarcHeader::SegmentTotalLength] {
+        if header(h.clone()).is_some() {
+            report.push(Error, Some(h), "only allowed in continuation records");
+        }
+    }
+}
+
+#[cfg(feature = "digest")]
+fn check_digests(record: &RecordRef<'_>, report: &mut RecordReport) {
+    let header = |h: &WarcHeader| record.header(h).and_then(utf8).map(str::trim);
+    let is_segment = record.header(&WarcHeader::SegmentNumber).is_some();
+
+    // a segment's digests describe the reassembled block, which is checked elsewhere
+    if let (Some(value), false) = (header(&WarcHeader::BlockDigest), is_segment) {
+        check_digest(WarcHeader::BlockDigest, value, record.body(), report);
+    }
+
+    // a revisit's payload digest is that of the record it revisits, and a truncated payload
+    // cannot match the digest of the whole
+    let verifies_payload = matches!(
+        report.record_type,
+        Some(RecordType::Response) | Some(RecordType::Resource)
+    ) && !is_segment
+        && record.header(&WarcHeader::Truncated).is_none();
+    if let (Some(value), true) = (header(&WarcHeader::PayloadDigest), verifies_payload) {
+        let body = record.body();
+        let is_http = header(&WarcHeader::ContentType)
+            .and_then(|t| MediaType::parse(t).ok())
+            .is_some_and(|t| t.is("application", "http"));
+        let payload = if is_http {
+            body.windows(4)
+                .position(|w| w == b"\r\n\r\n")
+                .map(|end| &body[end + 4..])
+        } else {
+            Some(body)
+        };
+
+        if let Some(payload) = payload {
+            check_digest(WarcHeader::PayloadDigest, value, payload, report);
+        }
+    }
+}
+
+#[cfg(feature = "digest")]
+fn check_digest(header: WarcHeader, value: &str, data: &[u8], report: &mut RecordReport) {
+    use sha2::Digest;
+
+    let (algorithm, digest) = match value.split_once(':') {
+        Some(parts) => parts,
+        None => return,
+    };
+    let hash = match algorithm.to_ascii_lowercase().as_str() {
+        "sha1" | "sha-1" => sha1::Sha1::digest(data).to_vec(),
+        "sha256" | "sha-256" => sha2::Sha256::digest(data).to_vec(),
+        "sha512" | "sha-512" => sha2::Sha512::digest(data).to_vec(),
+        _ => {
+            report.push(
+                Severity::Warning,
+                Some(header),
+                format!("unsupported algorithm {}, not verified", algorithm),
+            );
+            return;
+        }
+    };
+
+    let digest = digest.trim_end_matches('=');
+    let hex = hash
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    if !digest.eq_ignore_ascii_case(&base32(&hash)) && !digest.eq_ignore_ascii_case(&hex) {
+        report.push(Severity::Error, Some(header), "does not match the data");
+    }
+}
+
+/// Encode bytes as RFC 4648 base 32, without padding.
+#[cfg(feature = "digest")]
+fn base32(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in data {
+        buffer = ((buffer << 8) | byte as u32) & 0xffff;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_slice, Severity};
+    use crate::{RecordBuilder, RecordType, WarcHeader, WarcWriter};
+
+    use chrono::prelude::*;
+
+    fn issues(data: &[u8]) -> Vec<(Option<WarcHeader>, Severity)> {
+        validate_slice(data).records()[0]
+            .issues()
+            .iter()
+            .map(|i| (i.header().cloned(), i.severity()))
+            .collect()
+    }
+
+    fn record(headers: &str, body: &str) -> Vec<u8> {
+        format!(
+            "WARC/1.1\r\n\
+            WARC-Record-ID: <urn:uuid:00000000-0000-0000-0000-000000000000>\r\n\
+            WARC-Date: 2020-07-08T02:52:55Z\r\n\
+            {}Content-Length: {}\r\n\r\n{}\r\n\r\n",
+            headers,
+            body.len(),
+            body
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn valid_records() {
+        let mut data = Vec::new();
+        let mut writer = WarcWriter::new(&mut data);
+        let date = Utc.with_ymd_and_hms(2020, 7, 8, 2, 52, 55).unwrap();
+        let response = RecordBuilder::default()
+            .warc_type(RecordType::Response)
+            .date(date)
+            .header(WarcHeader::TargetURI, "http://example.com/")
+            .header(WarcHeader::ContentType, "text/plain")
+            .body(vec![b'x'; 100])
+            .build()
+            .unwrap();
+        for segment in response.into_segments(40) {
+            writer.write(&segment).unwrap();
+        }
+
+        let report = validate_slice(&data);
+        assert_eq!(report.records().len(), 3);
+        assert_eq!(report.errors() + report.warnings(), 0, "{}", report);
+    }
+
+    #[test]
+    fn header_rules() {
+        assert_eq!(
+            issues(&record("WARC-Type: resource\r\n", "")),
+            vec![(Some(WarcHeader::TargetURI), Severity::Error)]
+        );
+        assert_eq!(
+            issues(&record(
+                "WARC-Type: warcinfo\r\nWARC-Concurrent-To: <urn:x:1>\r\n",
+                "a"
+            )),
+            vec![
+                (Some(WarcHeader::ContentType), Severity::Warning),
+                (Some(WarcHeader::ConcurrentTo), Severity::Error),
+            ]
+        );
+        assert_eq!(
+            issues(&record(
+                "WARC-Type: metadata\r\nWARC-Truncated: bored\r\nContent-Type: text\r\n",
+                ""
+            )),
+            vec![
+                (Some(WarcHeader::ContentType), Severity::Error),
+                (Some(WarcHeader::Truncated), Severity::Warning),
+            ]
+        );
+        assert_eq!(
+            issues(
+                &String::from_utf8(record("WARC-Type: metadata\r\n", ""))
+                    .unwrap()
+                    .replace("<urn:uuid:00000000-0000-0000-0000-000000000000>", "foo")
+                    .into_bytes()
+            ),
+            vec![(Some(WarcHeader::RecordID), Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn target_uri_rules() {
+        let target_uri = |uri: &str| {
+            issues(&record(
+                &format!("WARC-Type: metadata\r\nWARC-Target-URI: {}\r\n", uri),
+                "",
+            ))
+        };
+
+        assert!(target_uri("http://example.com/a%20b").is_empty());
+        assert!(target_uri("<http://example.com/>").is_empty());
+        assert_eq!(
+            target_uri("http://example.com/caf\u{e9}"),
+            vec![(Some(WarcHeader::TargetURI), Severity::Warning)]
+        );
+        assert_eq!(
+            target_uri("http://example.com/100%"),
+            vec![(Some(WarcHeader::TargetURI), Severity::Warning)]
+        );
+        assert_eq!(
+            target_uri("not a uri"),
+            vec![(Some(WarcHeader::TargetURI), Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn segment_rules() {
+        let mut data = record("WARC-Type: metadata\r\nWARC-Segment-Number: 1\r\n", "");
+        let offset = data.len() as u64;
+        data.extend(record(
+            "WARC-Type: continuation\r\nWARC-Target-URI: http://example.com/\r\n\
+            WARC-Segment-Origin-ID: <urn:uuid:00000000-0000-0000-0000-000000000000>\r\n\
+            WARC-Segment-Number: 3\r\nWARC-Segment-Total-Length: 5\r\n",
+            "abc",
+        ));
+
+        let report = validate_slice(&data);
+        let headers = report.records()[1]
+            .issues()
+            .iter()
+            .map(|i| i.header().cloned().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            headers,
+            vec![
+                WarcHeader::RecordID,
+                WarcHeader::SegmentNumber,
+                WarcHeader::SegmentTotalLength
+            ]
+        );
+        assert_eq!(report.records()[1].offset(), offset);
+    }
+
+    #[cfg(feature = "digest")]
+    #[test]
+    fn digests() {
+        let body = "HTTP/1.1 200 OK\r\n\r\nhello";
+        let headers = |block: &str, payload: &str| {
+            format!(
+                "WARC-Type: response\r\nWARC-Target-URI: http://example.com/\r\n\
+                Content-Type: application/http\r\n\
+                WARC-Block-Digest: {}\r\nWARC-Payload-Digest: {}\r\n",
+                block, payload
+            )
+        };
+
+        let valid = headers(
+            "sha1:IORUMWLIBUO53GZZJS7FEOU3IDD3AFBH",
+            "sha1:aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d",
+        );
+        assert!(issues(&record(&valid, body)).is_empty());
+
+        let invalid = headers("sha1:AAAA", "md5:5d41402abc4b2a76b9719d911017c592");
+        assert_eq!(
+            issues(&record(&invalid, body)),
+            vec![
+                (Some(WarcHeader::BlockDigest), Severity::Error),
+                (Some(WarcHeader::PayloadDigest), Severity::Warning),
+            ]
+        );
+    }
+}
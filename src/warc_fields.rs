@@ -0,0 +1,280 @@
+use std::io;
+use std::io::Write;
+use std::iter::FromIterator;
+
+use crate::header::WarcHeader;
+use crate::parser;
+use crate::record_type::RecordType;
+use crate::{BufferedBody, Error, Record, RecordBuilder};
+
+/// The media type of an `application/warc-fields` body.
+pub(crate) const WARC_FIELDS_CONTENT_TYPE: &str = "application/warc-fields";
+
+/// An ordered list of named fields, as used by `application/warc-fields` bodies.
+///
+/// This is the format of `warcinfo` record bodies, and of most `metadata` records, such as the
+/// `outlink:` and `via:` lists written by Heritrix.
+///
+/// Fields are kept in the order they were added or read, and a field name may appear more than
+/// once. Field names are matched case-sensitively, as they are written by most tools.
+///
+/// Use the `Display` trait, `to_bytes` or `write_to` to generate the formatted representation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WarcFields {
+    fields: Vec<(String, String)>,
+}
+
+impl WarcFields {
+    /// Create a new, empty list of fields.
+    pub fn new() -> Self {
+        WarcFields::default()
+    }
+
+    /// Parse a list of fields from its `application/warc-fields` representation.
+    ///
+    /// Values folded over continuation lines are unfolded, with each line break and the leading
+    /// whitespace of the following line replaced by a single space.
+    pub fn parse(body: &[u8]) -> Result<Self, Error> {
+        let parsed = match parser::warc_fields(body) {
+            Err(e) => {
+                return Err(Error::ParseHeaders(
+                    e.map(|inner| (inner.input.to_owned(), inner.code)),
+                ))
+            }
+            Ok(parsed) => parsed.1,
+        };
+
+        let mut fields = Vec::with_capacity(parsed.len());
+        for (name, value) in parsed {
+            let value = std::str::from_utf8(value).map_err(|_| {
+                Error::MalformedHeader(WarcHeader::from(name), "not a UTF-8 string".to_string())
+            })?;
+            fields.push((name.to_owned(), WarcFields::unfold(value)));
+        }
+
+        Ok(WarcFields { fields })
+    }
+
+    fn unfold(value: &str) -> String {
+        value
+            .lines()
+            .map(|line| line.trim_start_matches([' ', '\t']))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Append a field, keeping any existing fields with the same name.
+    pub fn add<N: Into<String>, V: Into<String>>(&mut self, name: N, value: V) {
+        self.fields.push((name.into(), value.into()));
+    }
+
+    /// Replace all fields with the given name by a single field, returning the previous values.
+    ///
+    /// The new field takes the position of the first replaced field, or is appended if there was
+    /// none.
+    pub fn set<N: Into<String>, V: Into<String>>(&mut self, name: N, value: V) -> Vec<String> {
+        let name = name.into();
+        let position = self.fields.iter().position(|(key, _)| key == &name);
+        let removed = self.remove(&name);
+        let position = position.unwrap_or(self.fields.len());
+        self.fields.insert(position, (name, value.into()));
+
+        removed
+    }
+
+    /// Remove all fields with the given name, returning their values.
+    pub fn remove(&mut self, name: &str) -> Vec<String> {
+        let mut removed = Vec::new();
+        self.fields.retain(|(key, value)| {
+            if key == name {
+                removed.push(value.clone());
+                false
+            } else {
+                true
+            }
+        });
+
+        removed
+    }
+
+    /// Return the first value of the named field, or `None`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Return all values of the named field, in order.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.fields
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Return an iterator over all fields, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Return the number of fields.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Return `true` if there are no fields.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Write the `application/warc-fields` representation of these fields.
+    ///
+    /// Values containing line breaks are folded over continuation lines.
+    ///
+    /// The number of bytes written is returned upon success.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<usize> {
+        let mut bytes_written = 0;
+        for (name, value) in self.fields.iter() {
+            bytes_written += w.write(name.as_bytes())?;
+            bytes_written += w.write(&[58, 32])?;
+            for (i, line) in value.lines().enumerate() {
+                if i > 0 {
+                    bytes_written += w.write(&[13, 10, 32])?;
+                }
+                bytes_written += w.write(line.as_bytes())?;
+            }
+            bytes_written += w.write(&[13, 10])?;
+        }
+
+        Ok(bytes_written)
+    }
+
+    /// Generate the `application/warc-fields` representation of these fields.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)
+            .expect("writing to a Vec never fails");
+
+        bytes
+    }
+
+    /// Start building a record of the given type with these fields as its body.
+    ///
+    /// The `Content-Type` header is set to `application/warc-fields`.
+    pub fn into_record_builder(self, warc_type: RecordType) -> RecordBuilder {
+        RecordBuilder::default()
+            .warc_type(warc_type)
+            .header(WarcHeader::ContentType, WARC_FIELDS_CONTENT_TYPE)
+            .body(self.to_bytes())
+    }
+}
+
+impl std::fmt::Display for WarcFields {
+    fn fmt(&self, w: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        w.write_str(&String::from_utf8_lossy(&self.to_bytes()))
+    }
+}
+
+impl<N: Into<String>, V: Into<String>> FromIterator<(N, V)> for WarcFields {
+    fn from_iter<I: IntoIterator<Item = (N, V)>>(iter: I) -> Self {
+        WarcFields {
+            fields: iter
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+impl std::convert::TryFrom<&Record<BufferedBody>> for WarcFields {
+    type Error = Error;
+    fn try_from(record: &Record<BufferedBody>) -> Result<Self, Error> {
+        WarcFields::parse(record.body())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WarcFields;
+    use crate::{RecordType, WarcHeader};
+
+    use std::convert::TryFrom;
+
+    #[test]
+    fn parse_metadata() {
+        let raw = b"\
+            via: http://example.com/\r\n\
+            hopsFromSeed: L\r\n\
+            outlink: http://example.com/a L a/@href\r\n\
+            outlink: http://example.com/b.css E link/@href\r\n\
+            note: folded over\r\n\
+            \x20 two lines\r\n\
+        ";
+
+        let fields = WarcFields::parse(&raw[..]).unwrap();
+        assert_eq!(fields.len(), 5);
+        assert_eq!(fields.get("via"), Some("http://example.com/"));
+        assert_eq!(
+            fields.get_all("outlink").collect::<Vec<_>>(),
+            [
+                "http://example.com/a L a/@href",
+                "http://example.com/b.css E link/@href"
+            ]
+        );
+        assert_eq!(fields.get("note"), Some("folded over two lines"));
+        assert_eq!(
+            fields.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            ["via", "hopsFromSeed", "outlink", "outlink", "note"]
+        );
+    }
+
+    #[test]
+    fn modify() {
+        let mut fields: WarcFields = vec![("a", "1"), ("b", "2"), ("a", "3")]
+            .into_iter()
+            .collect();
+
+        assert_eq!(fields.set("a", "4"), ["1", "3"]);
+        assert_eq!(fields.iter().collect::<Vec<_>>(), [("a", "4"), ("b", "2")]);
+        assert_eq!(fields.remove("b"), ["2"]);
+        assert!(fields.remove("b").is_empty());
+        fields.add("c", "5");
+        assert_eq!(fields.iter().collect::<Vec<_>>(), [("a", "4"), ("c", "5")]);
+    }
+
+    #[test]
+    fn write() {
+        let mut fields = WarcFields::new();
+        fields.add("outlink", "http://example.com/ L a/@href");
+        fields.add("note", "first line\nsecond line");
+
+        assert_eq!(
+            fields.to_bytes(),
+            b"outlink: http://example.com/ L a/@href\r\n\
+              note: first line\r\n second line\r\n"
+                .to_vec()
+        );
+        assert_eq!(WarcFields::parse(&fields.to_bytes()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn round_trip_record() {
+        let mut fields = WarcFields::new();
+        fields.add("via", "http://example.com/");
+
+        let record = fields
+            .clone()
+            .into_record_builder(RecordType::Metadata)
+            .build()
+            .unwrap();
+        assert_eq!(record.warc_type(), &RecordType::Metadata);
+        assert_eq!(
+            record.header(WarcHeader::ContentType).unwrap(),
+            "application/warc-fields"
+        );
+        assert_eq!(WarcFields::try_from(&record).unwrap(), fields);
+    }
+}
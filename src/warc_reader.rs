@@ -1,10 +1,16 @@
 use crate::parser;
-use crate::{BufferedBody, Error, RawRecordHeader, Record, StreamingBody};
-
-use std::convert::TryInto;
+use crate::timestamp::DateRange;
+#[cfg(feature = "arrow")]
+use crate::RecordBatchIter;
+use crate::{
+    BufferedBody, EmptyBody, Error, RawRecordHeader, Record, RecordType, SegmentedRecordIter,
+    StreamingBody, WarcHeader,
+};
+
+use std::convert::{TryFrom, TryInto};
 use std::fs;
 use std::io;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read, Seek};
 use std::path::Path;
 
 #[cfg(feature = "gzip")]
@@ -13,15 +19,129 @@ use libflate::gzip::MultiDecoder as GzipReader;
 const KB: usize = 1_024;
 const MB: usize = 1_048_576;
 
+/// A function which advances a reader by a number of bytes.
+pub(crate) type SkipFn<R> = fn(&mut R, u64) -> io::Result<()>;
+
+/// The headers and location of a record, and the start of its body if it was read.
+pub(crate) type PrefixedHeader = (RawRecordHeader, RecordLocation, Option<Vec<u8>>);
+
+fn skip_by_reading<R: BufRead>(reader: &mut R, mut len: u64) -> io::Result<()> {
+    while len > 0 {
+        let available = reader.fill_buf()?.len() as u64;
+        if available == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let skipped = std::cmp::min(available, len);
+        reader.consume(skipped as usize);
+        len -= skipped;
+    }
+
+    Ok(())
+}
+
+fn skip_by_seeking<R: BufRead + Seek>(reader: &mut R, len: u64) -> io::Result<()> {
+    let offset = i64::try_from(len).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    reader.seek_relative(offset)
+}
+
+/// Skip the rest of a record body, and the line endings which terminate the record.
+pub(crate) fn skip_record_body<R: BufRead>(
+    reader: &mut R,
+    skip: SkipFn<R>,
+    len: u64,
+) -> Result<(), Error> {
+    match skip(reader, len) {
+        Ok(()) => (),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            return Err(Error::UnexpectedEOB)
+        }
+        Err(io) => return Err(Error::ReadData(io)),
+    }
+
+    let mut crlfs = [0; 4];
+
+    match reader.read_exact(&mut crlfs) {
+        Ok(()) => (),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            return Err(Error::UnexpectedEOB)
+        }
+        Err(io) => return Err(Error::ReadData(io)),
+    }
+
+    if &crlfs == b"\x0d\x0a\x0d\x0a" {
+        Ok(())
+    } else {
+        let synthetic_err: nom::Err<(Vec<u8>, nom::error::ErrorKind)> =
+            nom::Err::Failure((vec![0x0d, 0x0a, 0x0d, 0x0a], nom::error::ErrorKind::Tag));
+        Err(Error::ParseHeaders(synthetic_err))
+    }
+}
+
+/// Read the header block of the next record into a buffer, and parse it.
+///
+/// Returns the headers and the length of the body which follows them. The buffer holds the whole
+/// header block afterwards.
+pub(crate) fn read_header_block<R: BufRead>(
+    reader: &mut R,
+    buffer: &mut Vec<u8>,
+) -> Option<Result<(RawRecordHeader, u64), Error>> {
+    buffer.clear();
+    let mut found_headers = false;
+    while !found_headers {
+        let bytes_read = match reader.read_until(b'\n', buffer) {
+            Err(io) => return Some(Err(Error::ReadData(io))),
+            Ok(len) => len,
+        };
+
+        if bytes_read == 0 {
+            return None;
+        }
+
+        if bytes_read == 2 {
+            let last_two_chars = buffer.len() - 2;
+            if &buffer[last_two_chars..] == b"\r\n" {
+                found_headers = true;
+            }
+        }
+    }
+
+    let headers_parsed = match parser::headers(buffer) {
+        Err(e) => {
+            return Some(Err(Error::ParseHeaders(
+                e.map(|inner| (inner.input.to_owned(), inner.code)),
+            )))
+        }
+        Ok(parsed) => parsed.1,
+    };
+
+    let headers = RawRecordHeader {
+        version: headers_parsed.0.to_owned(),
+        headers: headers_parsed
+            .1
+            .into_iter()
+            .map(|(token, value)| (token.into(), value.to_owned()))
+            .collect(),
+    };
+
+    Some(Ok((headers, headers_parsed.2 as u64)))
+}
+
 /// A reader which iteratively parses WARC records from a stream.
 pub struct WarcReader<R> {
     reader: R,
+    skip: SkipFn<R>,
 }
 
 impl<R: BufRead> WarcReader<R> {
     /// Create a new reader.
+    ///
+    /// Skipped record bodies are read and discarded. Use `new_seekable` for inputs which support
+    /// seeking.
     pub fn new(r: R) -> Self {
-        WarcReader { reader: r }
+        WarcReader {
+            reader: r,
+            skip: skip_by_reading,
+        }
     }
 
     /// Create an iterator over all of the raw records read.
@@ -40,26 +160,68 @@ impl<R: BufRead> WarcReader<R> {
         RecordIter::new(self.reader)
     }
 
+    /// Create an iterator over the records read whose date falls within a range.
+    ///
+    /// Records outside of the range are still read in full. See `DateRangeIter` for more
+    /// information.
+    pub fn iter_records_between(self, range: DateRange) -> DateRangeIter<RecordIter<R>> {
+        DateRangeIter::new(self.iter_records(), range)
+    }
+
+    /// Create an iterator over the headers of all of the records read, with their locations.
+    ///
+    /// Record bodies are never read into memory; they are skipped as efficiently as the input
+    /// allows. See `HeaderIter` for more information.
+    pub fn iter_headers(self) -> HeaderIter<R> {
+        HeaderIter::new(self.reader, self.skip)
+    }
+
+    /// Create an iterator over all of the records read, reassembling segmented records.
+    ///
+    /// See `SegmentedRecordIter` for more information.
+    pub fn iter_reassembled_records(self) -> SegmentedRecordIter<RecordIter<R>> {
+        SegmentedRecordIter::new(self.iter_records())
+    }
+
     /// Create a streaming iterator over all of the records read.
     ///
     /// This will build each record header, and allow the caller to decide whether to read
     /// the body or not.
     pub fn stream_records(&mut self) -> StreamingIter<'_, R> {
-        StreamingIter::new(&mut self.reader)
+        StreamingIter::new(&mut self.reader, self.skip)
+    }
+
+    /// Create an iterator over Arrow record batches of the metadata of the records read.
+    ///
+    /// See `RecordBatchIter` for more information.
+    #[cfg(feature = "arrow")]
+    pub fn iter_record_batches(self, batch_size: usize) -> RecordBatchIter<R> {
+        RecordBatchIter::new(self, batch_size)
+    }
+}
+
+impl<R: BufRead + Seek> WarcReader<R> {
+    /// Create a new reader over a seekable input.
+    ///
+    /// Record bodies which are skipped while streaming are skipped by seeking over them, instead
+    /// of reading them. This is much faster for large, uncompressed files.
+    pub fn new_seekable(r: R) -> Self {
+        WarcReader {
+            reader: r,
+            skip: skip_by_seeking,
+        }
     }
 }
 
 impl WarcReader<BufReader<fs::File>> {
     /// Create a new reader which reads from file.
+    ///
+    /// The file is read as a seekable input. See `new_seekable` for more information.
     pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let file = fs::OpenOptions::new()
-            .read(true)
-            .create(true)
-            .truncate(false)
-            .open(&path)?;
+        let file = fs::File::open(&path)?;
         let reader = BufReader::with_capacity(MB, file);
 
-        Ok(WarcReader::new(reader))
+        Ok(WarcReader::new_seekable(reader))
     }
 }
 
@@ -79,11 +241,25 @@ impl WarcReader<BufReader<GzipReader<BufReader<std::fs::File>>>> {
 /// An iterator of raw records streamed from a reader. See `RawRecord` for more information.
 pub struct RawRecordIter<R> {
     reader: R,
+    warcinfo: Option<(RawRecordHeader, Vec<u8>)>,
 }
 
 impl<R: BufRead> RawRecordIter<R> {
     pub(crate) fn new(reader: R) -> RawRecordIter<R> {
-        RawRecordIter { reader }
+        RawRecordIter {
+            reader,
+            warcinfo: None,
+        }
+    }
+
+    /// Return the most recent `warcinfo` record read.
+    ///
+    /// This is the `warcinfo` record associated with the record last returned by the iterator,
+    /// which may be that record itself.
+    pub fn warcinfo(&self) -> Option<(&RawRecordHeader, &[u8])> {
+        self.warcinfo
+            .as_ref()
+            .map(|(headers, body)| (headers, body.as_slice()))
     }
 }
 
@@ -92,36 +268,12 @@ impl<R: BufRead> Iterator for RawRecordIter<R> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let mut header_buffer: Vec<u8> = Vec::with_capacity(64 * KB);
-        let mut found_headers = false;
-        while !found_headers {
-            let bytes_read = match self.reader.read_until(b'\n', &mut header_buffer) {
-                Err(io) => return Some(Err(Error::ReadData(io))),
-                Ok(len) => len,
+        let (headers, expected_body_len) =
+            match read_header_block(&mut self.reader, &mut header_buffer)? {
+                Ok(block) => block,
+                Err(e) => return Some(Err(e)),
             };
-
-            if bytes_read == 0 {
-                return None;
-            }
-
-            if bytes_read == 2 {
-                let last_two_chars = header_buffer.len() - 2;
-                if &header_buffer[last_two_chars..] == b"\r\n" {
-                    found_headers = true;
-                }
-            }
-        }
-
-        let headers_parsed = match parser::headers(&header_buffer) {
-            Err(e) => {
-                return Some(Err(Error::ParseHeaders(
-                    e.map(|inner| (inner.input.to_owned(), inner.code)),
-                )))
-            }
-            Ok(parsed) => parsed.1,
-        };
-        let version_ref = headers_parsed.0;
-        let headers_ref = headers_parsed.1;
-        let expected_body_len = headers_parsed.2;
+        let expected_body_len = expected_body_len as usize;
 
         let mut body_buffer: Vec<u8> = Vec::with_capacity(MB);
         let mut found_body = false;
@@ -150,27 +302,154 @@ impl<R: BufRead> Iterator for RawRecordIter<R> {
         }
 
         let body_ref = &body_buffer[..expected_body_len];
-
-        let headers = RawRecordHeader {
-            version: version_ref.to_owned(),
-            headers: headers_ref
-                .into_iter()
-                .map(|(token, value)| (token.into(), value.to_owned()))
-                .collect(),
-        };
         let body = body_ref.to_owned();
+        let is_warcinfo = headers
+            .as_ref()
+            .get(&WarcHeader::WarcType)
+            .map(|t| RecordType::from(String::from_utf8_lossy(t)) == RecordType::WarcInfo)
+            .unwrap_or(false);
+        if is_warcinfo {
+            self.warcinfo = Some((headers.clone(), body.clone()));
+        }
         Some(Ok((headers, body)))
     }
 }
 
+/// The location of a record within a stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecordLocation {
+    /// The number of bytes before the start of the record.
+    pub offset: u64,
+    /// The length of the record, including its header block and the line endings after its body.
+    pub length: u64,
+}
+
+/// An iterator over the headers of the records read by a reader.
+///
+/// Each item is a raw record header, and the location of the record in the stream read. For
+/// compressed inputs, locations are relative to the decompressed stream.
+pub struct HeaderIter<R> {
+    reader: R,
+    skip: SkipFn<R>,
+    offset: u64,
+    header_buffer: Vec<u8>,
+}
+
+impl<R: BufRead> HeaderIter<R> {
+    pub(crate) fn new(reader: R, skip: SkipFn<R>) -> HeaderIter<R> {
+        HeaderIter {
+            reader,
+            skip,
+            offset: 0,
+            header_buffer: Vec::with_capacity(KB),
+        }
+    }
+
+    /// Read the headers of the next record, and the start of its body if `read_prefix` returns
+    /// `true` for them.
+    ///
+    /// Up to `limit` bytes of the body are read; the rest of it is skipped.
+    pub(crate) fn next_with_prefix<F>(
+        &mut self,
+        limit: u64,
+        read_prefix: F,
+    ) -> Option<Result<PrefixedHeader, Error>>
+    where
+        F: FnOnce(&RawRecordHeader) -> bool,
+    {
+        let (headers, expected_body_len) =
+            match read_header_block(&mut self.reader, &mut self.header_buffer)? {
+                Ok(block) => block,
+                Err(e) => return Some(Err(e)),
+            };
+
+        let mut prefix = None;
+        if read_prefix(&headers) {
+            let mut buffer = Vec::new();
+            let read = (&mut self.reader)
+                .take(expected_body_len.min(limit))
+                .read_to_end(&mut buffer);
+            if let Err(e) = read {
+                return Some(Err(Error::ReadData(e)));
+            }
+            prefix = Some(buffer);
+        }
+        let prefix_len = prefix.as_ref().map_or(0, |prefix| prefix.len() as u64);
+
+        let body_bytes_left = expected_body_len - prefix_len;
+        if let Err(e) = skip_record_body(&mut self.reader, self.skip, body_bytes_left) {
+            return Some(Err(e));
+        }
+
+        let location = RecordLocation {
+            offset: self.offset,
+            length: self.header_buffer.len() as u64 + expected_body_len + 4,
+        };
+        self.offset += location.length;
+
+        Some(Ok((headers, location, prefix)))
+    }
+}
+
+impl<R: BufRead> Iterator for HeaderIter<R> {
+    type Item = Result<(RawRecordHeader, RecordLocation), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.next_with_prefix(0, |_| false)?;
+        Some(next.map(|(headers, location, _)| (headers, location)))
+    }
+}
+
 /// An iterator which returns the records read by a reader.
 pub struct RecordIter<R> {
     reader: R,
+    warcinfo: Option<Record<BufferedBody>>,
 }
 
 impl<R: BufRead> RecordIter<R> {
     pub(crate) fn new(reader: R) -> RecordIter<R> {
-        RecordIter { reader }
+        RecordIter {
+            reader,
+            warcinfo: None,
+        }
+    }
+
+    /// Return the most recent `warcinfo` record read.
+    ///
+    /// This is the `warcinfo` record associated with the record last returned by the iterator,
+    /// which may be that record itself.
+    pub fn warcinfo(&self) -> Option<&Record<BufferedBody>> {
+        self.warcinfo.as_ref()
+    }
+}
+
+/// An iterator which returns the records of another iterator whose date falls within a range.
+///
+/// Errors are always returned.
+pub struct DateRangeIter<I> {
+    inner: I,
+    range: DateRange,
+}
+
+impl<I> DateRangeIter<I> {
+    /// Wrap an iterator over records.
+    pub fn new(inner: I, range: DateRange) -> Self {
+        DateRangeIter { inner, range }
+    }
+}
+
+impl<I> Iterator for DateRangeIter<I>
+where
+    I: Iterator<Item = Result<Record<BufferedBody>, Error>>,
+{
+    type Item = Result<Record<BufferedBody>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let range = &self.range;
+        self.inner.find(|record| match record {
+            Ok(record) => range.contains(record.date()),
+            Err(_) => true,
+        })
     }
 }
 
@@ -179,37 +458,12 @@ impl<R: BufRead> Iterator for RecordIter<R> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let mut header_buffer: Vec<u8> = Vec::with_capacity(64 * KB);
-        let mut found_headers = false;
-        while !found_headers {
-            let bytes_read = match self.reader.read_until(b'\n', &mut header_buffer) {
-                Err(io) => return Some(Err(Error::ReadData(io))),
-                Ok(len) => len,
+        let (headers, expected_body_len) =
+            match read_header_block(&mut self.reader, &mut header_buffer)? {
+                Ok(block) => block,
+                Err(e) => return Some(Err(e)),
             };
-
-            if bytes_read == 0 {
-                return None;
-            }
-
-            if bytes_read == 2 {
-                let last_two_chars = header_buffer.len() - 2;
-                if &header_buffer[last_two_chars..] == b"\r\n" {
-                    found_headers = true;
-                }
-            }
-        }
-
-        let headers_parsed = match parser::headers(&header_buffer) {
-            Err(e) => {
-                return Some(Err(Error::ParseHeaders(
-                    e.map(|inner| (inner.input.to_owned(), inner.code)),
-                )));
-            }
-
-            Ok(parsed) => parsed.1,
-        };
-        let version_ref = headers_parsed.0;
-        let headers_ref = headers_parsed.1;
-        let expected_body_len = headers_parsed.2;
+        let expected_body_len = expected_body_len as usize;
 
         let mut body_buffer: Vec<u8> = Vec::with_capacity(MB);
         let mut found_body = false;
@@ -238,19 +492,15 @@ impl<R: BufRead> Iterator for RecordIter<R> {
         }
 
         let body_ref = &body_buffer[..expected_body_len];
-
-        let headers = RawRecordHeader {
-            version: version_ref.to_owned(),
-            headers: headers_ref
-                .into_iter()
-                .map(|(token, value)| (token.into(), value.to_owned()))
-                .collect(),
-        };
         let body = body_ref.to_owned();
         match headers.try_into() {
             Ok(b) => {
                 let buffered: Record<_> = b;
-                Some(Ok(buffered.add_body(body)))
+                let record = buffered.add_body(body);
+                if record.warc_type() == &RecordType::WarcInfo {
+                    self.warcinfo = Some(record.clone());
+                }
+                Some(Ok(record))
             }
             Err(e) => Some(Err(e)),
         }
@@ -264,118 +514,189 @@ impl<R: BufRead> Iterator for RecordIter<R> {
 ///
 /// This is streaming iterator is particularly useful for streams of records which are indefinite
 /// or contain and records of unknown size.
+///
+/// Bodies which are not read are skipped when advancing to the next item. If the reader was
+/// created with `WarcReader::new_seekable`, they are skipped by seeking.
 pub struct StreamingIter<'r, R> {
     reader: &'r mut R,
+    skip: SkipFn<R>,
     current_item_size: u64,
     first_record: bool,
+    warcinfo: Option<Record<BufferedBody>>,
+    // NB: the body of the current item, if it is a warcinfo record, is captured as it is read
+    pending_warcinfo: Option<Record<EmptyBody>>,
+    warcinfo_body: Vec<u8>,
 }
 
-impl<R: BufRead> StreamingIter<'_, R> {
-    pub(crate) fn new(reader: &mut R) -> StreamingIter<'_, R> {
+impl<'r, R: BufRead> StreamingIter<'r, R> {
+    pub(crate) fn new(reader: &'r mut R, skip: SkipFn<R>) -> StreamingIter<'r, R> {
         StreamingIter {
             reader,
+            skip,
             current_item_size: 0,
             first_record: true,
+            warcinfo: None,
+            pending_warcinfo: None,
+            warcinfo_body: Vec::new(),
         }
     }
 
+    /// Return the most recent `warcinfo` record read.
+    ///
+    /// This is the `warcinfo` record associated with the record last returned. The body of a
+    /// `warcinfo` record is kept as it is read, and the rest of it is read instead of skipped, so
+    /// the record itself is only returned here once the stream has advanced past it.
+    pub fn warcinfo(&self) -> Option<&Record<BufferedBody>> {
+        self.warcinfo.as_ref()
+    }
+
     fn skip_body(&mut self) -> Result<(), Error> {
-        let mut read_buffer = [0u8; MB];
-        let maximum_read_range = self.current_item_size;
-        let mut body_bytes_left = maximum_read_range;
-        while body_bytes_left > 0 {
-            let read_size = std::cmp::min(body_bytes_left, read_buffer.len() as u64) as usize;
-            let bytes_read = match self.reader.read(&mut read_buffer[..read_size]) {
-                Err(io) => return Err(Error::ReadData(io)),
-                Ok(len) => len as u64,
-            };
-            if bytes_read == 0 {
+        if let Some(record) = self.pending_warcinfo.take() {
+            let left = std::mem::replace(&mut self.current_item_size, 0);
+            let read = (&mut *self.reader)
+                .take(left)
+                .read_to_end(&mut self.warcinfo_body)
+                .map_err(Error::ReadData)?;
+            if (read as u64) < left {
                 return Err(Error::UnexpectedEOB);
             }
-            body_bytes_left -= bytes_read;
+            self.warcinfo = Some(record.add_body(std::mem::take(&mut self.warcinfo_body)));
         }
 
-        let mut crlfs = [0; 4];
+        let body_bytes_left = std::mem::replace(&mut self.current_item_size, 0);
+        skip_record_body(self.reader, self.skip, body_bytes_left)
+    }
+
+    /// Advance the stream to the headers of the next item, skipping the rest of the current body.
+    fn next_header(&mut self) -> Option<Result<Record<EmptyBody>, Error>> {
+        if self.first_record {
+            self.first_record = false;
+        } else if let Err(e) = self.skip_body() {
+            return Some(Err(e));
+        }
 
-        match self.reader.read_exact(&mut crlfs) {
-            Ok(()) => (),
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                return Err(Error::UnexpectedEOB)
+        let mut header_buffer: Vec<u8> = Vec::with_capacity(64 * KB);
+        let headers = match read_header_block(self.reader, &mut header_buffer)? {
+            Ok((headers, expected_body_len)) => {
+                self.current_item_size = expected_body_len;
+                headers
             }
-            Err(io) => return Err(Error::ReadData(io)),
+            Err(e) => return Some(Err(e)),
+        };
+        let record: Record<EmptyBody> = match headers.try_into() {
+            Ok(record) => record,
+            Err(e) => return Some(Err(e)),
+        };
+        if record.warc_type() == &RecordType::WarcInfo {
+            self.warcinfo_body.clear();
+            self.pending_warcinfo = Some(record.clone());
         }
 
-        if &crlfs == b"\x0d\x0a\x0d\x0a" {
-            Ok(())
-        } else {
-            let synthetic_err: nom::Err<(Vec<u8>, nom::error::ErrorKind)> =
-                nom::Err::Failure((vec![0x0d, 0x0a, 0x0d, 0x0a], nom::error::ErrorKind::Tag));
-            Err(Error::ParseHeaders(synthetic_err))
+        Some(Ok(record))
+    }
+
+    fn add_stream(
+        &mut self,
+        record: Record<EmptyBody>,
+    ) -> Result<Record<StreamingBody<'_, R>>, Error> {
+        if self.pending_warcinfo.is_none() {
+            return record
+                .add_fixed_stream(self.reader, &mut self.current_item_size)
+                .map_err(Error::ReadData);
         }
+
+        Ok(record.add_streaming_body(StreamingBody::with_capture(
+            self.reader,
+            &mut self.current_item_size,
+            &mut self.warcinfo_body,
+        )))
     }
 
     /// Advance the stream to the next item.
     ///
+    /// Any part of the body of the previous item which was not read is skipped.
+    ///
     /// Returns one of the following:
     /// * Some(Ok(r))` is the next record read from the stream.
     /// * `Some(Err)` indicates there was a read error.
     /// * `None` indicates no more records are returned.
     pub fn next_item(&mut self) -> Option<Result<Record<StreamingBody<'_, R>>, Error>> {
-        if self.first_record {
-            self.first_record = false;
-        } else if let Err(e) = self.skip_body() {
-            return Some(Err(e));
+        match self.next_header()? {
+            Ok(record) => Some(self.add_stream(record)),
+            Err(e) => Some(Err(e)),
         }
+    }
 
-        let mut header_buffer: Vec<u8> = Vec::with_capacity(64 * KB);
-        let mut found_headers = false;
-        while !found_headers {
-            let bytes_read = match self.reader.read_until(b'\n', &mut header_buffer) {
-                Err(io) => return Some(Err(Error::ReadData(io))),
-                Ok(len) => len,
-            };
+    /// Call a closure on each remaining item of the stream.
+    ///
+    /// The closure may read as much of the body of each record as it needs; the rest is skipped.
+    /// Iteration stops at the first error, either read from the stream or returned by the
+    /// closure.
+    pub fn for_each<F>(&mut self, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(&mut Record<StreamingBody<'_, R>>) -> Result<(), Error>,
+    {
+        while let Some(record) = self.next_item() {
+            f(&mut record?)?;
+        }
 
-            if bytes_read == 0 {
-                return None;
-            }
+        Ok(())
+    }
 
-            if bytes_read == 2 {
-                let last_two_chars = header_buffer.len() - 2;
-                if &header_buffer[last_two_chars..] == b"\r\n" {
-                    found_headers = true;
+    /// Create a streaming iterator over the items whose headers match a predicate.
+    ///
+    /// The predicate is called with the headers of each record before a body is attached. The
+    /// bodies of rejected records are skipped without being read by the caller.
+    pub fn filter_headers<P>(&mut self, predicate: P) -> FilterHeaders<'_, 'r, R, P>
+    where
+        P: FnMut(&Record<EmptyBody>) -> bool,
+    {
+        FilterHeaders {
+            iter: self,
+            predicate,
+        }
+    }
+}
+
+/// A streaming iterator over the items of a `StreamingIter` whose headers match a predicate.
+///
+/// See `StreamingIter::filter_headers` for more information.
+pub struct FilterHeaders<'i, 'r, R, P> {
+    iter: &'i mut StreamingIter<'r, R>,
+    predicate: P,
+}
+
+impl<R: BufRead, P> FilterHeaders<'_, '_, R, P>
+where
+    P: FnMut(&Record<EmptyBody>) -> bool,
+{
+    /// Advance the stream to the next matching item.
+    ///
+    /// See `StreamingIter::next_item` for more information.
+    pub fn next_item(&mut self) -> Option<Result<Record<StreamingBody<'_, R>>, Error>> {
+        loop {
+            match self.iter.next_header()? {
+                Ok(record) if (self.predicate)(&record) => {
+                    return Some(self.iter.add_stream(record))
                 }
+                Ok(_) => continue,
+                Err(e) => return Some(Err(e)),
             }
         }
+    }
 
-        let headers_parsed = match parser::headers(&header_buffer) {
-            Err(e) => {
-                return Some(Err(Error::ParseHeaders(
-                    e.map(|inner| (inner.input.to_owned(), inner.code)),
-                )))
-            }
-            Ok(parsed) => parsed.1,
-        };
-        let version_ref = headers_parsed.0;
-        let headers_ref = headers_parsed.1;
-        self.current_item_size = headers_parsed.2 as u64;
-
-        let headers = RawRecordHeader {
-            version: version_ref.to_owned(),
-            headers: headers_ref
-                .into_iter()
-                .map(|(token, value)| (token.into(), value.to_owned()))
-                .collect(),
-        };
-        match headers.try_into() {
-            Ok(b) => {
-                let record: Record<_> = b;
-                let fixed_stream_result = record
-                    .add_fixed_stream(self.reader, &mut self.current_item_size)
-                    .map_err(Error::ReadData);
-                Some(fixed_stream_result)
-            }
-            Err(e) => Some(Err(e)),
+    /// Call a closure on each remaining matching item of the stream.
+    ///
+    /// See `StreamingIter::for_each` for more information.
+    pub fn for_each<F>(&mut self, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(&mut Record<StreamingBody<'_, R>>) -> Result<(), Error>,
+    {
+        while let Some(record) = self.next_item() {
+            f(&mut record?)?;
         }
+
+        Ok(())
     }
 }
 
@@ -487,10 +808,93 @@ mod iter_raw_tests {
 }
 
 #[cfg(test)]
-mod next_item_tests {
+mod iter_headers_tests {
     use std::io::{BufReader, Cursor};
 
-    use crate::WarcReader;
+    use crate::{RecordLocation, WarcHeader, WarcReader};
+
+    #[test]
+    fn headers_and_locations() {
+        let raw = b"\
+            WARC/1.0\r\n\
+            Warc-Type: dunno\r\n\
+            Content-Length: 5\r\n\
+            WARC-Record-Id: <urn:test:two-records:record-0>\r\n\
+            WARC-Date: 2020-07-08T02:52:55Z\r\n\
+            \r\n\
+            12345\r\n\
+            \r\n\
+            WARC/1.0\r\n\
+            Warc-Type: another\r\n\
+            WARC-Record-Id: <urn:test:two-records:record-1>\r\n\
+            WARC-Date: 2020-07-08T02:52:56Z\r\n\
+            Content-Length: 6\r\n\
+            \r\n\
+            123456\r\n\
+            \r\n\
+        ";
+
+        for reader in [
+            WarcReader::new(BufReader::new(Cursor::new(&raw[..]))),
+            WarcReader::new_seekable(BufReader::new(Cursor::new(&raw[..]))),
+        ] {
+            let headers = reader
+                .iter_headers()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(headers.len(), 2);
+
+            let (first, first_location) = &headers[0];
+            assert_eq!(
+                first.as_ref().get(&WarcHeader::RecordID).unwrap(),
+                b"<urn:test:two-records:record-0>"
+            );
+            assert_eq!(
+                first_location,
+                &RecordLocation {
+                    offset: 0,
+                    length: 140
+                }
+            );
+
+            let (second, second_location) = &headers[1];
+            assert_eq!(
+                second.as_ref().get(&WarcHeader::WarcType).unwrap(),
+                b"another"
+            );
+            assert_eq!(
+                second_location,
+                &RecordLocation {
+                    offset: 140,
+                    length: raw.len() as u64 - 140
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn truncated_body() {
+        let raw = b"\
+            WARC/1.0\r\n\
+            Warc-Type: dunno\r\n\
+            Content-Length: 50\r\n\
+            \r\n\
+            12345\r\n\
+            \r\n\
+        ";
+
+        let reader = WarcReader::new(BufReader::new(Cursor::new(&raw[..])));
+        assert!(reader.iter_headers().next().unwrap().is_err());
+    }
+}
+
+#[cfg(test)]
+mod next_item_tests {
+    use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+
+    use crate::{Error, RecordBuilder, RecordType, WarcReader, WarcWriter};
+
+    use chrono::prelude::*;
 
     macro_rules! create_reader {
         ($raw:expr) => {{
@@ -525,6 +929,47 @@ mod next_item_tests {
         assert_eq!(record.body(), b"12345");
     }
 
+    #[test]
+    fn warcinfo() {
+        let date = Utc.with_ymd_and_hms(2020, 7, 8, 2, 52, 55).unwrap();
+        let warcinfo = RecordBuilder::default()
+            .warc_type(RecordType::WarcInfo)
+            .date(date)
+            .body(b"software: test\r\n".to_vec())
+            .build()
+            .unwrap();
+        let response = RecordBuilder::default()
+            .warc_type(RecordType::Response)
+            .body(b"12345".to_vec())
+            .build()
+            .unwrap();
+
+        let mut raw = Vec::new();
+        {
+            let mut writer = WarcWriter::new(&mut raw);
+            for record in [&warcinfo, &response, &warcinfo, &response] {
+                writer.write(record).unwrap();
+            }
+        }
+
+        let mut reader = WarcReader::new(create_reader!(raw));
+        let mut stream_iter = reader.stream_records();
+        assert!(stream_iter.warcinfo().is_none());
+
+        // NB: the first warcinfo body is partly read, the second is skipped
+        let mut record = stream_iter.next_item().unwrap().unwrap();
+        let mut start = [0; 4];
+        record.read_exact(&mut start).unwrap();
+        assert_eq!(&start, b"soft");
+        stream_iter.next_item().unwrap().unwrap();
+        assert_eq!(stream_iter.warcinfo(), Some(&warcinfo));
+
+        let mut filtered = stream_iter.filter_headers(|r| r.warc_type() != &RecordType::WarcInfo);
+        filtered.next_item().unwrap().unwrap();
+        assert!(filtered.next_item().is_none());
+        assert_eq!(stream_iter.warcinfo().unwrap().body(), warcinfo.body());
+    }
+
     #[test]
     fn both_items() {
         let raw = b"\
@@ -757,4 +1202,199 @@ mod next_item_tests {
             assert_eq!(record.body(), b"1234567");
         }
     }
+
+    #[test]
+    fn skip_and_finish() {
+        let raw = b"\
+            WARC/1.0\r\n\
+            Warc-Type: dunno\r\n\
+            Content-Length: 5\r\n\
+            WARC-Record-Id: <urn:test:two-records:record-0>\r\n\
+            WARC-Date: 2020-07-08T02:52:55Z\r\n\
+            \r\n\
+            12345\r\n\
+            \r\n\
+            WARC/1.0\r\n\
+            Warc-Type: another\r\n\
+            WARC-Record-Id: <urn:test:two-records:record-1>\r\n\
+            WARC-Date: 2020-07-08T02:52:56Z\r\n\
+            Content-Length: 6\r\n\
+            \r\n\
+            123456\r\n\
+            \r\n\
+        ";
+
+        let mut reader = WarcReader::new(create_reader!(raw));
+        let mut stream_iter = reader.stream_records();
+
+        let mut record = stream_iter.next_item().unwrap().unwrap();
+        let mut buf = [0u8; 2];
+        record.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"12");
+        assert_eq!(record.skip().unwrap(), 3);
+
+        let mut record = stream_iter.next_item().unwrap().unwrap();
+        record.read_exact(&mut buf).unwrap();
+        assert!(record.finish().is_err());
+        assert!(stream_iter.next_item().is_none());
+    }
+
+    #[test]
+    fn for_each() {
+        let raw = b"\
+            WARC/1.0\r\n\
+            Warc-Type: dunno\r\n\
+            Content-Length: 5\r\n\
+            WARC-Record-Id: <urn:test:three-records:record-0>\r\n\
+            WARC-Date: 2020-07-08T02:52:55Z\r\n\
+            \r\n\
+            12345\r\n\
+            \r\n\
+            WARC/1.0\r\n\
+            Warc-Type: another\r\n\
+            WARC-Record-Id: <urn:test:three-records:record-1>\r\n\
+            WARC-Date: 2020-07-08T02:52:56Z\r\n\
+            Content-Length: 6\r\n\
+            \r\n\
+            123456\r\n\
+            \r\n\
+            WARC/1.0\r\n\
+            Warc-Type: dunno\r\n\
+            WARC-Record-Id: <urn:test:three-records:record-2>\r\n\
+            WARC-Date: 2020-07-08T02:52:56Z\r\n\
+            Content-Length: 8\r\n\
+            \r\n\
+            12345678\r\n\
+            \r\n\
+        ";
+
+        let mut reader = WarcReader::new(create_reader!(raw));
+        let mut ids = Vec::new();
+        reader
+            .stream_records()
+            .for_each(|record| {
+                ids.push(record.warc_id().to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(
+            ids,
+            [
+                "<urn:test:three-records:record-0>",
+                "<urn:test:three-records:record-1>",
+                "<urn:test:three-records:record-2>"
+            ]
+        );
+
+        let mut reader = WarcReader::new(create_reader!(raw));
+        let mut bodies = Vec::new();
+        reader
+            .stream_records()
+            .filter_headers(|record| record.warc_type() == &RecordType::from("dunno"))
+            .for_each(|record| {
+                let mut body = Vec::new();
+                record.read_to_end(&mut body).map_err(Error::ReadData)?;
+                bodies.push(body);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(bodies, [b"12345".to_vec(), b"12345678".to_vec()]);
+    }
+
+    #[test]
+    fn seek_over_bodies() {
+        struct CountingReader {
+            inner: Cursor<Vec<u8>>,
+            bytes_read: usize,
+        }
+        impl Read for CountingReader {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = self.inner.read(buf)?;
+                self.bytes_read += n;
+                Ok(n)
+            }
+        }
+        impl Seek for CountingReader {
+            fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+                self.inner.seek(pos)
+            }
+        }
+
+        let mut raw = Vec::new();
+        for i in 0..3 {
+            let body = vec![b'x'; 64 * 1024];
+            let record = RecordBuilder::default()
+                .warc_id(format!("<urn:test:seek-over-bodies:record-{}>", i))
+                .body(body)
+                .build()
+                .unwrap();
+            let mut writer = WarcWriter::new(&mut raw);
+            writer.write(&record).unwrap();
+        }
+
+        let counting = CountingReader {
+            inner: Cursor::new(raw.clone()),
+            bytes_read: 0,
+        };
+        let mut reader = WarcReader::new_seekable(BufReader::with_capacity(1024, counting));
+        let mut stream_iter = reader.stream_records();
+        let mut ids = Vec::new();
+        while let Some(record) = stream_iter.next_item() {
+            ids.push(record.unwrap().warc_id().to_string());
+        }
+        assert_eq!(
+            ids,
+            [
+                "<urn:test:seek-over-bodies:record-0>",
+                "<urn:test:seek-over-bodies:record-1>",
+                "<urn:test:seek-over-bodies:record-2>"
+            ]
+        );
+        let bytes_read = reader.reader.into_inner().bytes_read;
+        assert!(bytes_read < raw.len() / 4);
+    }
+}
+
+#[cfg(test)]
+mod date_range_tests {
+    use std::io::{BufReader, Cursor};
+
+    use crate::timestamp::DateRange;
+    use crate::WarcReader;
+
+    #[test]
+    fn records_between() {
+        let raw = b"\
+            WARC/1.0\r\n\
+            Warc-Type: dunno\r\n\
+            Content-Length: 5\r\n\
+            WARC-Record-Id: <urn:test:two-records:record-0>\r\n\
+            WARC-Date: 2020-07-08T02:52:55Z\r\n\
+            \r\n\
+            12345\r\n\
+            \r\n\
+            WARC/1.0\r\n\
+            Warc-Type: another\r\n\
+            WARC-Record-Id: <urn:test:two-records:record-1>\r\n\
+            WARC-Date: 2020-07-08T02:52:56Z\r\n\
+            Content-Length: 6\r\n\
+            \r\n\
+            123456\r\n\
+            \r\n\
+        ";
+
+        let between = |start, end| {
+            let reader = WarcReader::new(BufReader::new(Cursor::new(&raw[..])));
+            reader
+                .iter_records_between(DateRange::from_timestamps(start, end).unwrap())
+                .map(|record| record.unwrap().warc_id().to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(between("2020", "2020").len(), 2);
+        assert_eq!(
+            between("20200708025256", "2021"),
+            ["<urn:test:two-records:record-1>"]
+        );
+        assert!(between("2019", "20200708025254").is_empty());
+    }
 }
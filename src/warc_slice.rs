@@ -0,0 +1,283 @@
+use std::convert::TryFrom;
+
+use crate::header::WarcHeader;
+use crate::parser;
+use crate::{BufferedBody, EmptyBody, Error, RawRecordHeader, Record, RecordLocation};
+
+/// A record whose headers and body borrow from the data it was parsed from.
+///
+/// Like `RawRecordHeader`, a borrowed record is guaranteed to be well-formed, but may not be
+/// valid according to the specification.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordRef<'a> {
+    version: &'a str,
+    headers: Vec<(&'a str, &'a [u8])>,
+    body: &'a [u8],
+    location: RecordLocation,
+}
+
+impl<'a> RecordRef<'a> {
+    /// Return the WARC standard version this record reports conformance to.
+    pub fn version(&self) -> &'a str {
+        self.version
+    }
+
+    /// Return all headers of this record, in the order they were read.
+    pub fn headers(&self) -> &[(&'a str, &'a [u8])] {
+        &self.headers
+    }
+
+    /// Return the value of the first occurrence of a header, or `None`.
+    pub fn header(&self, header: &WarcHeader) -> Option<&'a [u8]> {
+        let name = header.as_str();
+        self.headers
+            .iter()
+            .find(|(token, _)| token.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+
+    /// Return the body of this record.
+    pub fn body(&self) -> &'a [u8] {
+        self.body
+    }
+
+    /// Return the location of this record within the data it was parsed from.
+    pub fn location(&self) -> RecordLocation {
+        self.location
+    }
+
+    /// Copy the headers of this record into a raw record header.
+    pub fn to_raw_header(&self) -> RawRecordHeader {
+        RawRecordHeader {
+            version: self.version.to_owned(),
+            headers: self
+                .headers
+                .iter()
+                .map(|(token, value)| ((*token).into(), value.to_vec()))
+                .collect(),
+        }
+    }
+
+    /// Copy this record into a record with a buffered body, checking it for semantic
+    /// correctness.
+    pub fn to_record(&self) -> Result<Record<BufferedBody>, Error> {
+        Record::<EmptyBody>::try_from(self.to_raw_header()).map(|r| r.add_body(self.body))
+    }
+}
+
+/// A reader which parses WARC records directly from a byte slice, without copying.
+///
+/// This is most useful for uncompressed WARC files which are memory-mapped, or otherwise already
+/// in memory.
+#[derive(Clone, Copy, Debug)]
+pub struct WarcSlice<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> WarcSlice<'a> {
+    /// Create a new reader over a byte slice.
+    pub fn new(data: &'a [u8]) -> Self {
+        WarcSlice { data }
+    }
+
+    /// Parse the record starting at the given offset.
+    pub fn record_at(&self, offset: usize) -> Result<RecordRef<'a>, Error> {
+        let input = self.data.get(offset..).ok_or(Error::UnexpectedEOB)?;
+        let (rest, (version, headers, body)) = match parser::record(input) {
+            Err(nom::Err::Incomplete(_)) => return Err(Error::UnexpectedEOB),
+            Err(e) => {
+                return Err(Error::ParseHeaders(
+                    e.map(|inner| (inner.input.to_owned(), inner.code)),
+                ))
+            }
+            Ok(parsed) => parsed,
+        };
+
+        Ok(RecordRef {
+            version,
+            headers,
+            body,
+            location: RecordLocation {
+                offset: offset as u64,
+                length: (input.len() - rest.len()) as u64,
+            },
+        })
+    }
+
+    /// Create an iterator over all of the records in the slice.
+    pub fn iter(&self) -> SliceRecordIter<'a> {
+        SliceRecordIter {
+            slice: *self,
+            offset: 0,
+        }
+    }
+}
+
+impl<'a> IntoIterator for WarcSlice<'a> {
+    type Item = Result<RecordRef<'a>, Error>;
+    type IntoIter = SliceRecordIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the records borrowed from a byte slice.
+///
+/// Iteration stops after the first error.
+pub struct SliceRecordIter<'a> {
+    slice: WarcSlice<'a>,
+    offset: usize,
+}
+
+impl<'a> Iterator for SliceRecordIter<'a> {
+    type Item = Result<RecordRef<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.slice.data.len() {
+            return None;
+        }
+
+        match self.slice.record_at(self.offset) {
+            Ok(record) => {
+                self.offset += record.location().length as usize;
+                Some(Ok(record))
+            }
+            Err(e) => {
+                self.offset = self.slice.data.len();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A memory-mapped WARC file.
+///
+/// # Safety
+///
+/// The file must not be modified, by this or any other process, while it is mapped. Doing so is
+/// undefined behavior.
+#[cfg(feature = "mmap")]
+pub struct MappedWarc {
+    map: memmap2::Mmap,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedWarc {
+    /// Map an uncompressed WARC file into memory.
+    ///
+    /// # Safety
+    ///
+    /// See the type documentation: the file must not be modified while it is mapped.
+    pub unsafe fn open<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let map = memmap2::Mmap::map(&file)?;
+
+        Ok(MappedWarc { map })
+    }
+
+    /// Return the mapped bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Create a reader over the mapped bytes.
+    pub fn slice(&self) -> WarcSlice<'_> {
+        WarcSlice::new(&self.map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WarcSlice;
+    use crate::{Error, RecordLocation, WarcHeader};
+
+    const RAW: &[u8] = b"\
+        WARC/1.0\r\n\
+        Warc-Type: dunno\r\n\
+        Content-Length: 5\r\n\
+        WARC-Record-Id: <urn:test:two-records:record-0>\r\n\
+        WARC-Date: 2020-07-08T02:52:55Z\r\n\
+        \r\n\
+        12345\r\n\
+        \r\n\
+        WARC/1.0\r\n\
+        Warc-Type: another\r\n\
+        WARC-Record-Id: <urn:test:two-records:record-1>\r\n\
+        WARC-Date: 2020-07-08T02:52:56Z\r\n\
+        Content-Length: 6\r\n\
+        \r\n\
+        123456\r\n\
+        \r\n\
+    ";
+
+    #[test]
+    fn borrowed_records() {
+        let records = WarcSlice::new(RAW)
+            .iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+
+        let first = &records[0];
+        assert_eq!(first.version(), "1.0");
+        assert_eq!(first.header(&WarcHeader::WarcType), Some(&b"dunno"[..]));
+        assert_eq!(first.body(), b"12345");
+        assert_eq!(
+            first.location(),
+            RecordLocation {
+                offset: 0,
+                length: 140
+            }
+        );
+        // the body points into the original data
+        assert_eq!(first.body().as_ptr(), RAW[131..].as_ptr());
+
+        let second = &records[1];
+        assert_eq!(second.body(), b"123456");
+        assert_eq!(second.location().offset, 140);
+
+        let record = second.to_record().unwrap();
+        assert_eq!(record.warc_id(), "<urn:test:two-records:record-1>");
+        assert_eq!(record.body(), b"123456");
+    }
+
+    #[test]
+    fn random_access() {
+        let slice = WarcSlice::new(RAW);
+        let record = slice.record_at(140).unwrap();
+        assert_eq!(
+            record.header(&WarcHeader::RecordID),
+            Some(&b"<urn:test:two-records:record-1>"[..])
+        );
+        assert!(slice.record_at(1).is_err());
+        assert!(slice.record_at(RAW.len() + 1).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        let mut iter = WarcSlice::new(&RAW[..RAW.len() - 3]).iter();
+        assert!(iter.next().unwrap().is_ok());
+        match iter.next() {
+            Some(Err(Error::UnexpectedEOB)) => (),
+            _ => panic!("expected unexpected end of body"),
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mapped_file() {
+        use super::MappedWarc;
+
+        let path = std::env::temp_dir().join(format!("{}.warc", uuid::Uuid::new_v4()));
+        std::fs::write(&path, RAW).unwrap();
+
+        let mapped = unsafe { MappedWarc::open(&path) }.unwrap();
+        assert_eq!(mapped.as_bytes(), RAW);
+        assert_eq!(mapped.slice().iter().count(), 2);
+
+        drop(mapped);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{BufferedBody, RawRecordHeader, Record};
+use crate::{BufferedBody, RawRecordHeader, Record, RecordType, WarcHeader};
+
+#[cfg(feature = "gzip")]
+use crate::WarcWriterOptions;
 
 use std::fs;
 use std::io;
@@ -10,52 +13,173 @@ use libflate::gzip::Encoder as GzipWriter;
 
 const MB: usize = 1_048_576;
 
+/// Write a record with `write_raw`, as several segments if its body is longer than the maximum
+/// body length.
+pub(crate) fn write_segmented<F>(
+    record: &Record<BufferedBody>,
+    max_body_len: Option<usize>,
+    mut write_raw: F,
+) -> io::Result<usize>
+where
+    F: FnMut(RawRecordHeader, Vec<u8>) -> io::Result<usize>,
+{
+    match max_body_len {
+        Some(max_body_len) if record.content_length() > max_body_len as u64 => {
+            let mut bytes_written = 0;
+            for segment in record.clone().into_segments(max_body_len) {
+                let (headers, body) = segment.into_raw_parts();
+                bytes_written += write_raw(headers, body)?;
+            }
+
+            Ok(bytes_written)
+        }
+        _ => {
+            let (headers, body) = record.clone().into_raw_parts();
+            write_raw(headers, body)
+        }
+    }
+}
+
 /// A writer which writes records to an output stream.
 pub struct WarcWriter<W> {
     writer: W,
+    warcinfo_id: Option<Vec<u8>>,
+    link_warcinfo: bool,
+    max_body_len: Option<usize>,
+    #[cfg(feature = "gzip")]
+    options: Option<WarcWriterOptions>,
 }
 
 impl<W: Write> WarcWriter<W> {
     /// Create a new writer.
     pub fn new(w: W) -> Self {
-        WarcWriter { writer: w }
+        WarcWriter {
+            writer: w,
+            warcinfo_id: None,
+            link_warcinfo: false,
+            max_body_len: None,
+            #[cfg(feature = "gzip")]
+            options: None,
+        }
+    }
+
+    /// Create a new writer which compresses every record into its own GZIP member.
+    #[cfg(feature = "gzip")]
+    pub fn with_options(w: W, options: WarcWriterOptions) -> Self {
+        WarcWriter {
+            options: Some(options),
+            ..WarcWriter::new(w)
+        }
+    }
+
+    /// Set the maximum body length of records written by `write`.
+    ///
+    /// Records whose body is longer are split into a first segment and `continuation` records,
+    /// as described by `Record::into_segments`. By default, records are never split. Records
+    /// written by `write_raw` are never split.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the maximum body length is zero.
+    pub fn set_max_body_length(&mut self, max_body_len: Option<usize>) {
+        assert_ne!(
+            max_body_len,
+            Some(0),
+            "segments must have a non-zero length"
+        );
+        self.max_body_len = max_body_len;
+    }
+
+    /// Set whether records written should reference the last `warcinfo` record written.
+    ///
+    /// When enabled, a `WARC-Warcinfo-ID` header with the record ID of the last `warcinfo` record
+    /// written is added to every following record which does not already have one. This is
+    /// disabled by default.
+    pub fn set_link_warcinfo(&mut self, link_warcinfo: bool) {
+        self.link_warcinfo = link_warcinfo;
+    }
+
+    /// Return the record ID of the last `warcinfo` record written, if any.
+    pub fn warcinfo_id(&self) -> Option<&str> {
+        self.warcinfo_id
+            .as_ref()
+            .and_then(|id| std::str::from_utf8(id).ok())
+    }
+
+    #[cfg(feature = "rayon")]
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
     }
 
     /// Write a single record.
     ///
+    /// If a maximum body length is set, the record may be written as several segments.
+    ///
     /// The number of bytes written is returned upon success.
     pub fn write(&mut self, record: &Record<BufferedBody>) -> io::Result<usize> {
-        let (headers, body) = record.clone().into_raw_parts();
-        self.write_raw(headers, &body)
+        let max_body_len = self.max_body_len;
+        write_segmented(record, max_body_len, |headers, body| {
+            self.write_raw(headers, &body)
+        })
     }
 
     /// Write a single raw record.
     ///
-    /// The number of bytes written is returned upon success.
-    pub fn write_raw<B>(&mut self, headers: RawRecordHeader, body: &B) -> io::Result<usize>
+    /// The number of bytes written, before any compression, is returned upon success.
+    pub fn write_raw<B>(&mut self, mut headers: RawRecordHeader, body: &B) -> io::Result<usize>
     where
         B: AsRef<[u8]>,
     {
-        let mut bytes_written = 0;
+        let is_warcinfo = headers
+            .as_ref()
+            .get(&WarcHeader::WarcType)
+            .map(|t| RecordType::from(String::from_utf8_lossy(t)) == RecordType::WarcInfo)
+            .unwrap_or(false);
+        if is_warcinfo {
+            self.warcinfo_id = headers.as_ref().get(&WarcHeader::RecordID).cloned();
+        } else if self.link_warcinfo {
+            if let Some(ref id) = self.warcinfo_id {
+                headers
+                    .as_mut()
+                    .entry(WarcHeader::WarcInfoID)
+                    .or_insert_with(|| id.clone());
+            }
+        }
 
-        bytes_written += self.writer.write(&[87, 65, 82, 67, 47])?;
-        bytes_written += self.writer.write(headers.version.as_bytes())?;
-        bytes_written += self.writer.write(&[13, 10])?;
+        #[cfg(feature = "gzip")]
+        if let Some(ref options) = self.options {
+            let mut serialized = Vec::new();
+            let bytes_written = write_record(&mut serialized, &headers, body.as_ref())?;
+            self.writer
+                .write_all(&options.compress_member(&serialized)?)?;
 
-        for (token, value) in headers.as_ref().iter() {
-            bytes_written += self.writer.write(token.to_string().as_bytes())?;
-            bytes_written += self.writer.write(&[58, 32])?;
-            bytes_written += self.writer.write(value)?;
-            bytes_written += self.writer.write(&[13, 10])?;
+            return Ok(bytes_written);
         }
-        bytes_written += self.writer.write(&[13, 10])?;
 
-        bytes_written += self.writer.write(body.as_ref())?;
-        bytes_written += self.writer.write(&[13, 10])?;
-        bytes_written += self.writer.write(&[13, 10])?;
+        write_record(&mut self.writer, &headers, body.as_ref())
+    }
+}
+
+fn write_record<W: Write>(w: &mut W, headers: &RawRecordHeader, body: &[u8]) -> io::Result<usize> {
+    let mut bytes_written = 0;
+
+    bytes_written += w.write(&[87, 65, 82, 67, 47])?;
+    bytes_written += w.write(headers.version.as_bytes())?;
+    bytes_written += w.write(&[13, 10])?;
 
-        Ok(bytes_written)
+    for (token, value) in headers.as_ref().iter() {
+        bytes_written += w.write(token.to_string().as_bytes())?;
+        bytes_written += w.write(&[58, 32])?;
+        bytes_written += w.write(value)?;
+        bytes_written += w.write(&[13, 10])?;
     }
+    bytes_written += w.write(&[13, 10])?;
+
+    bytes_written += w.write(body)?;
+    bytes_written += w.write(&[13, 10])?;
+    bytes_written += w.write(&[13, 10])?;
+
+    Ok(bytes_written)
 }
 
 impl<W: Write> WarcWriter<BufWriter<W>> {
@@ -89,6 +213,24 @@ impl WarcWriter<BufWriter<fs::File>> {
 
         Ok(WarcWriter::new(writer))
     }
+
+    /// Create a new writer which writes to a file, compressing every record into its own GZIP
+    /// member.
+    #[cfg(feature = "gzip")]
+    pub fn from_path_with_options<P: AsRef<Path>>(
+        path: P,
+        options: WarcWriterOptions,
+    ) -> io::Result<Self> {
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let writer = BufWriter::with_capacity(MB, file);
+
+        Ok(WarcWriter::with_options(writer, options))
+    }
 }
 
 #[cfg(feature = "gzip")]
@@ -107,3 +249,86 @@ impl WarcWriter<BufWriter<GzipWriter<std::fs::File>>> {
         Ok(WarcWriter::new(writer))
     }
 }
+
+#[cfg(test)]
+mod warcinfo_tests {
+    use crate::{RecordBuilder, RecordType, WarcHeader, WarcInfo, WarcReader, WarcWriter};
+
+    use std::io::{BufReader, Cursor};
+
+    #[test]
+    fn link_warcinfo() {
+        let warcinfo = WarcInfo::new()
+            .software("warc-rs")
+            .into_record(None)
+            .unwrap();
+        let response = RecordBuilder::default()
+            .warc_type(RecordType::Response)
+            .build()
+            .unwrap();
+
+        let mut writer = WarcWriter::new(Vec::new());
+        writer.write(&response).unwrap();
+        assert_eq!(writer.warcinfo_id(), None);
+
+        writer.set_link_warcinfo(true);
+        writer.write(&warcinfo).unwrap();
+        assert_eq!(writer.warcinfo_id(), Some(warcinfo.warc_id()));
+        writer.write(&response).unwrap();
+
+        let reader = WarcReader::new(BufReader::new(Cursor::new(writer.writer)));
+        let mut iter = reader.iter_records();
+
+        let record = iter.next().unwrap().unwrap();
+        assert!(record.header(WarcHeader::WarcInfoID).is_none());
+        assert!(iter.warcinfo().is_none());
+
+        let record = iter.next().unwrap().unwrap();
+        assert_eq!(record.warc_type(), &RecordType::WarcInfo);
+        assert!(record.header(WarcHeader::WarcInfoID).is_none());
+        assert_eq!(iter.warcinfo(), Some(&record));
+
+        let record = iter.next().unwrap().unwrap();
+        assert_eq!(
+            record.header(WarcHeader::WarcInfoID).unwrap(),
+            warcinfo.warc_id()
+        );
+        assert_eq!(iter.warcinfo().unwrap().warc_id(), warcinfo.warc_id());
+    }
+}
+
+#[cfg(test)]
+mod segment_tests {
+    use crate::{RecordBuilder, RecordType, WarcHeader, WarcReader, WarcWriter};
+
+    use std::io::{BufReader, Cursor};
+
+    #[test]
+    fn write_segments() {
+        let record = RecordBuilder::default()
+            .warc_type(RecordType::Resource)
+            .body(b"0123456789".to_vec())
+            .build()
+            .unwrap();
+
+        let mut writer = WarcWriter::new(Vec::new());
+        writer.set_max_body_length(Some(4));
+        writer.write(&record).unwrap();
+
+        let reader = WarcReader::new(BufReader::new(Cursor::new(writer.writer)));
+        let segments = reader
+            .iter_records()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].warc_id(), record.warc_id());
+        assert_eq!(segments[0].body(), b"0123");
+        assert_eq!(segments[1].warc_type(), &RecordType::Continuation);
+        assert_eq!(segments[1].body(), b"4567");
+        assert_eq!(segments[2].body(), b"89");
+        assert_eq!(
+            segments[2].header(WarcHeader::SegmentTotalLength).unwrap(),
+            "10"
+        );
+    }
+}
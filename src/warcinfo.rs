@@ -0,0 +1,220 @@
+use std::convert::TryFrom;
+
+use crate::header::WarcHeader;
+use crate::record_type::RecordType;
+use crate::{BufferedBody, Error, Record, WarcFields};
+
+/// The structured body of a `warcinfo` record.
+///
+/// This is a thin wrapper around `WarcFields`, with setters for the fields recommended by the
+/// standard. Each setter replaces any value the field already had; use `field` to add repeated
+/// fields.
+///
+/// Use the `Display` trait, or `to_bytes`, to generate the formatted `application/warc-fields`
+/// representation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WarcInfo {
+    fields: WarcFields,
+}
+
+impl WarcInfo {
+    /// Create a new, empty warcinfo body.
+    pub fn new() -> Self {
+        WarcInfo::default()
+    }
+
+    /// Parse a warcinfo body from its `application/warc-fields` representation.
+    pub fn parse(body: &[u8]) -> Result<Self, Error> {
+        WarcFields::parse(body).map(WarcInfo::from)
+    }
+
+    /// Append an arbitrary field to the body under construction.
+    pub fn field<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.fields.add(name, value);
+
+        self
+    }
+
+    /// Replace any fields with the given name by a single field.
+    fn set_field<V: Into<String>>(mut self, name: &str, value: V) -> Self {
+        self.fields.set(name, value);
+
+        self
+    }
+
+    /// Set the `software` field of the body under construction.
+    pub fn software<S: Into<String>>(self, software: S) -> Self {
+        self.set_field("software", software)
+    }
+
+    /// Set the `hostname` field of the body under construction.
+    pub fn hostname<S: Into<String>>(self, hostname: S) -> Self {
+        self.set_field("hostname", hostname)
+    }
+
+    /// Set the `operator` field of the body under construction.
+    pub fn operator<S: Into<String>>(self, operator: S) -> Self {
+        self.set_field("operator", operator)
+    }
+
+    /// Set the `format` field of the body under construction.
+    pub fn format<S: Into<String>>(self, format: S) -> Self {
+        self.set_field("format", format)
+    }
+
+    /// Set the `conformsTo` field of the body under construction.
+    pub fn conforms_to<S: Into<String>>(self, conforms_to: S) -> Self {
+        self.set_field("conformsTo", conforms_to)
+    }
+
+    /// Set the `robots` field of the body under construction.
+    pub fn robots<S: Into<String>>(self, robots: S) -> Self {
+        self.set_field("robots", robots)
+    }
+
+    /// Set the `isPartOf` field of the body under construction.
+    pub fn is_part_of<S: Into<String>>(self, is_part_of: S) -> Self {
+        self.set_field("isPartOf", is_part_of)
+    }
+
+    /// Set the `description` field of the body under construction.
+    pub fn description<S: Into<String>>(self, description: S) -> Self {
+        self.set_field("description", description)
+    }
+
+    /// Return the first value of the named field, or `None`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields.get(name)
+    }
+
+    /// Return all fields of this body.
+    pub fn fields(&self) -> &WarcFields {
+        &self.fields
+    }
+
+    /// Return a reference to mutate the fields of this body.
+    pub fn fields_mut(&mut self) -> &mut WarcFields {
+        &mut self.fields
+    }
+
+    /// Generate the `application/warc-fields` representation of this body.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.fields.to_bytes()
+    }
+
+    /// Build a `warcinfo` record with this body.
+    ///
+    /// The `Content-Type` header is set to `application/warc-fields`. If a file name is given,
+    /// it is used for the `WARC-Filename` header.
+    pub fn into_record(self, filename: Option<&str>) -> Result<Record<BufferedBody>, Error> {
+        let mut builder = self.fields.into_record_builder(RecordType::WarcInfo);
+        if let Some(filename) = filename {
+            builder = builder.header(WarcHeader::Filename, filename);
+        }
+
+        builder.build()
+    }
+}
+
+impl std::fmt::Display for WarcInfo {
+    fn fmt(&self, w: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(w, "{}", self.fields)
+    }
+}
+
+impl From<WarcFields> for WarcInfo {
+    fn from(fields: WarcFields) -> Self {
+        WarcInfo { fields }
+    }
+}
+
+impl From<WarcInfo> for WarcFields {
+    fn from(info: WarcInfo) -> Self {
+        info.fields
+    }
+}
+
+impl TryFrom<&Record<BufferedBody>> for WarcInfo {
+    type Error = Error;
+    fn try_from(record: &Record<BufferedBody>) -> Result<Self, Error> {
+        if record.warc_type() != &RecordType::WarcInfo {
+            return Err(Error::MalformedHeader(
+                WarcHeader::WarcType,
+                "not a warcinfo record".to_string(),
+            ));
+        }
+
+        WarcInfo::parse(record.body())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WarcInfo;
+    use crate::{RecordType, WarcHeader};
+
+    use std::convert::TryFrom;
+
+    #[test]
+    fn build() {
+        let info = WarcInfo::new()
+            .software("warc-rs/0.4.0")
+            .format("WARC File Format 1.1")
+            .conforms_to(
+                "http://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/",
+            )
+            .field("x-custom", "one")
+            .field("x-custom", "two")
+            .software("warc-rs/0.4.0");
+
+        assert_eq!(info.get("software"), Some("warc-rs/0.4.0"));
+        assert_eq!(info.get("hostname"), None);
+        assert_eq!(
+            info.fields().get_all("x-custom").collect::<Vec<_>>(),
+            ["one", "two"]
+        );
+        assert_eq!(
+            info.to_string(),
+            "software: warc-rs/0.4.0\r\n\
+             format: WARC File Format 1.1\r\n\
+             conformsTo: http://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/\r\n\
+             x-custom: one\r\n\
+             x-custom: two\r\n"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let raw = b"\
+            software: Heritrix/3.4.0\r\n\
+            hostname: crawler-01\r\n\
+            isPartOf: a-crawl\r\n\
+            \r\n\
+        ";
+
+        let info = WarcInfo::parse(&raw[..]).unwrap();
+        assert_eq!(info.fields().len(), 3);
+        assert_eq!(info.get("software"), Some("Heritrix/3.4.0"));
+        assert_eq!(info.get("hostname"), Some("crawler-01"));
+        assert_eq!(info.get("isPartOf"), Some("a-crawl"));
+
+        assert!(WarcInfo::parse(b"no separator here\r\n").is_err());
+    }
+
+    #[test]
+    fn round_trip_record() {
+        let info = WarcInfo::new()
+            .hostname("localhost")
+            .operator("Jane Doe")
+            .description("a test crawl");
+
+        let record = info.clone().into_record(Some("test.warc.gz")).unwrap();
+        assert_eq!(record.warc_type(), &RecordType::WarcInfo);
+        assert_eq!(
+            record.header(WarcHeader::ContentType).unwrap(),
+            "application/warc-fields"
+        );
+        assert_eq!(record.header(WarcHeader::Filename).unwrap(), "test.warc.gz");
+        assert_eq!(WarcInfo::try_from(&record).unwrap(), info);
+    }
+}
@@ -0,0 +1,103 @@
+#![cfg(feature = "cli")]
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use chrono::prelude::*;
+use warc::{RecordBuilder, RecordType, WarcHeader, WarcWriter};
+
+fn warc(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_warc"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// A temporary file, removed when dropped.
+struct Fixture(PathBuf);
+
+impl Fixture {
+    fn new(data: &[u8]) -> Self {
+        let path = std::env::temp_dir().join(format!("{}.warc", uuid::Uuid::new_v4()));
+        std::fs::write(&path, data).unwrap();
+        Fixture(path)
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn records() -> Fixture {
+    let date = Utc.with_ymd_and_hms(2020, 7, 8, 2, 52, 55).unwrap();
+    let mut data = Vec::new();
+    let mut writer = WarcWriter::new(&mut data);
+    for (id, uri) in [
+        ("<urn:test:1>", "http://example.com/"),
+        ("<urn:test:2>", "http://example.com/a"),
+    ] {
+        let record = RecordBuilder::default()
+            .warc_id(id)
+            .warc_type(RecordType::Resource)
+            .date(date)
+            .header(WarcHeader::TargetURI, uri)
+            .header(WarcHeader::ContentType, "text/plain")
+            .body(b"hello".to_vec())
+            .build()
+            .unwrap();
+        writer.write(&record).unwrap();
+    }
+    drop(writer);
+
+    Fixture::new(&data)
+}
+
+#[test]
+fn ls() {
+    let fixture = records();
+    let output = warc(&["ls", fixture.path()]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("0\t"));
+    assert!(lines[1].ends_with("\tresource\t2020-07-08T02:52:55Z\thttp://example.com/a"));
+}
+
+#[test]
+fn index() {
+    let fixture = records();
+    let output = warc(&["index", fixture.path()]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], " CDX N b a m s k r M S V g");
+    assert!(lines[1].starts_with("com,example)/ 20200708025255 http://example.com/ text/plain"));
+    assert!(lines[2].starts_with("com,example)/a 20200708025255 http://example.com/a text/plain"));
+}
+
+#[test]
+fn bad_input() {
+    let fixture = Fixture::new(b"not a WARC file\r\n\r\n");
+    let output = warc(&["ls", fixture.path()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: "));
+
+    let output = warc(&["validate", fixture.path()]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = warc(&["cat", fixture.path()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("usage: warc"));
+
+    let output = warc(&["bogus"]);
+    assert_eq!(output.status.code(), Some(2));
+}